@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    bot::{Controller, GameState},
+    snake::Direction,
+    Point,
+};
+
+/// Names of the built-in players, as used by `snake tournament`.
+pub const BUILTIN: [&str; 2] = ["greedy", "random"];
+
+/// Built-in player by name.
+pub fn builtin(name: &str, seed: u64) -> Option<Box<dyn Controller>> {
+    match name {
+        "greedy" => Some(Box::new(Greedy)),
+        "random" => Some(Box::new(RandomAi::new(seed))),
+        _ => None,
+    }
+}
+
+/// Cells the snake can't go into, row by row.
+struct Blocked {
+    cells: Vec<bool>,
+    cols: usize,
+}
+
+impl Blocked {
+    fn new(state: &GameState) -> Self {
+        let mut blocked = Self {
+            cells: vec![false; state.width * state.height],
+            cols: state.width,
+        };
+        let snakes = state.snakes.iter().flat_map(|snake| snake.body.iter());
+        for point in state.walls.iter().chain(snakes) {
+            blocked.set(*point);
+        }
+        blocked
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.cells[point.y * self.cols + point.x]
+    }
+
+    /// Returns false if it was already set.
+    fn set(&mut self, point: Point) -> bool {
+        !std::mem::replace(&mut self.cells[point.y * self.cols + point.x], true)
+    }
+}
+
+fn neighbour(state: &GameState, point: Point, direction: Direction) -> Option<Point> {
+    let Point { x, y } = point;
+    match direction {
+        Direction::Left if x > 0 => Some(Point::new(x - 1, y)),
+        Direction::Up if y > 0 => Some(Point::new(x, y - 1)),
+        Direction::Right if x + 1 < state.width => Some(Point::new(x + 1, y)),
+        Direction::Down if y + 1 < state.height => Some(Point::new(x, y + 1)),
+        _ => None,
+    }
+}
+
+/// Moves which don't kill the snake right away, with the cell moved to.
+fn safe_moves(state: &GameState, blocked: &Blocked) -> Vec<(Direction, Point)> {
+    let head = state.snakes[state.you].body[0];
+    Direction::ALL
+        .into_iter()
+        .filter_map(|direction| {
+            neighbour(state, head, direction)
+                .filter(|next| !blocked.contains(next))
+                .map(|next| (direction, next))
+        })
+        .collect()
+}
+
+/// Free cells reachable from the point, counting stops at `limit`.
+fn room(state: &GameState, blocked: &Blocked, from: Point, limit: usize) -> usize {
+    let mut seen = Blocked {
+        cells: blocked.cells.clone(),
+        cols: blocked.cols,
+    };
+    seen.set(from);
+    let mut count = 1;
+    let mut queue = VecDeque::from([from]);
+    while let Some(point) = queue.pop_front() {
+        if count >= limit {
+            break;
+        }
+        for direction in Direction::ALL {
+            if let Some(next) = neighbour(state, point, direction) {
+                if seen.set(next) {
+                    count += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Goes to the nearest apple but not into a place too small for its body.
+pub struct Greedy;
+
+impl Controller for Greedy {
+    fn ask(&mut self, state: &GameState) -> Option<Direction> {
+        let blocked = Blocked::new(state);
+        let length = state.snakes[state.you].body.len();
+        let current = Direction::from_name(state.snakes[state.you].direction);
+        let distance = |point: Point| {
+            state
+                .apples
+                .iter()
+                .map(|apple| {
+                    apple.position.x.abs_diff(point.x) + apple.position.y.abs_diff(point.y)
+                })
+                .min()
+                .unwrap_or(0)
+        };
+        safe_moves(state, &blocked)
+            .into_iter()
+            .min_by_key(|(direction, next)| {
+                (
+                    room(state, &blocked, *next, length) < length,
+                    distance(*next),
+                    Some(*direction) != current,
+                )
+            })
+            .map(|(direction, _)| direction)
+    }
+
+    fn finish(&mut self, _state: &GameState) {}
+}
+
+/// Any move which doesn't kill it right away, same seed makes same moves.
+pub struct RandomAi {
+    rng: StdRng,
+}
+
+impl RandomAi {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Controller for RandomAi {
+    fn ask(&mut self, state: &GameState) -> Option<Direction> {
+        safe_moves(state, &Blocked::new(state))
+            .choose(&mut self.rng)
+            .map(|(direction, _)| *direction)
+    }
+
+    fn finish(&mut self, _state: &GameState) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apple::AppleDispencer, level::Level, snake::Snake, topbar::TopBar, Size};
+
+    fn state(walls: &[Point]) -> GameState {
+        let mut level = Level::empty(Size::new(10, 10));
+        for wall in walls {
+            level.toggle_wall(*wall);
+        }
+        // Snake is at 6..=3,6 going right
+        let snake = Snake::on_level(level.clone(), 100);
+        GameState::new(
+            0,
+            &snake,
+            &AppleDispencer::with_seed(0, 1, level),
+            &TopBar::new(),
+        )
+    }
+
+    #[test]
+    fn test_safe_moves() {
+        let state = state(&[Point::new(7, 6), Point::new(6, 5)]);
+        let moves = safe_moves(&state, &Blocked::new(&state));
+        assert_eq!(moves, [(Direction::Down, Point::new(6, 7))]);
+        assert_eq!(Greedy.ask(&state), Some(Direction::Down));
+        assert_eq!(RandomAi::new(3).ask(&state), Some(Direction::Down));
+    }
+
+    #[test]
+    fn test_greedy_avoids_dead_end() {
+        // Going down leads into a pocket of two cells
+        let walls = [
+            Point::new(7, 6),
+            Point::new(5, 7),
+            Point::new(7, 7),
+            Point::new(5, 8),
+            Point::new(7, 8),
+            Point::new(6, 9),
+        ];
+        assert_eq!(Greedy.ask(&state(&walls)), Some(Direction::Up));
+    }
+}
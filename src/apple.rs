@@ -1,29 +1,62 @@
-use std::{time::Duration, usize};
+use std::time::Duration;
 
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use crate::{frame::Drawable, timer::Timer, Point, NUM_COLS, NUM_ROWS};
+use crate::{
+    events::{EventQueue, GameEvent},
+    frame::Drawable,
+    level::Level,
+    timer::Timer,
+    Point,
+};
 
 const MIN_APPEAR_TIME: usize = 10;
 const MAX_APPEAR_TIME: usize = 20;
 
-#[derive(Clone, Copy)]
+/// Kind of an apple, sound packs can give each kind its own sound.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AppleKind {
+    Regular,
+}
+
+impl AppleKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppleKind::Regular => "regular",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 struct Apple {
     place: Point,
+    kind: AppleKind,
     timer: Timer,
 }
 
+/// Apples are placed from the game seed, so the same seed gives the same
+/// apples. Every apple gets its own generator made from the seed and the
+/// number of apples before it, this way saved game goes on with the same
+/// apples as well. Apples are never put on walls of the level.
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct AppleDispencer {
     deployed: Vec<Apple>,
     max_count: u8,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    spawned: u64,
+    #[serde(default)]
+    level: Level,
 }
 
 impl Apple {
-    fn new(place: Point) -> Self {
-        let mut rng = thread_rng();
+    fn new<R: Rng>(place: Point, kind: AppleKind, rng: &mut R) -> Self {
         let rand_time = rng.gen_range(MIN_APPEAR_TIME..=MAX_APPEAR_TIME);
         Self {
             place,
+            kind,
             timer: Timer::new(Duration::from_secs(rand_time as u64)),
         }
     }
@@ -48,32 +81,95 @@ impl Drawable for Apple {
 
 impl AppleDispencer {
     pub fn new(max_count: u8) -> Self {
+        Self::with_seed(max_count, thread_rng().gen(), Level::default())
+    }
+    pub fn with_seed(max_count: u8, seed: u64, level: Level) -> Self {
         let deployed = Vec::new();
         Self {
             max_count,
             deployed,
+            seed,
+            spawned: 0,
+            level,
+        }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn update(&mut self, delta: Duration, events: &mut EventQueue) {
+        if (self.deployed.len() as u8) < self.max_count {
+            if let Some(apple) = self.deploy() {
+                events.push(GameEvent::AppleSpawned {
+                    kind: apple.kind,
+                    pos: apple.place,
+                });
+            }
         }
+        self.deployed.retain_mut(|apple| {
+            let expired = apple.timer_update_check(delta);
+            if expired {
+                events.push(GameEvent::AppleExpired { pos: apple.place });
+            }
+            !expired
+        });
+    }
+    pub fn max_count(&self) -> u8 {
+        self.max_count
+    }
+    /// Place, kind and time left of every apple on the board.
+    pub fn apples(&self) -> impl Iterator<Item = (Point, AppleKind, Duration)> + '_ {
+        self.deployed
+            .iter()
+            .map(|apple| (apple.place, apple.kind, apple.timer.remaining()))
     }
-    pub fn update(&mut self, delta: Duration) {
+    /// Place of the apple closest to given point and time until it's gone.
+    pub fn nearest(&self, to: Point) -> Option<(Point, Duration)> {
+        self.deployed
+            .iter()
+            .min_by_key(|apple| apple.place.x.abs_diff(to.x) + apple.place.y.abs_diff(to.y))
+            .map(|apple| (apple.place, apple.timer.remaining()))
+    }
+    /// Game time until apples change by themselves: a missing one is
+    /// placed on the next update, others expire or their seconds left go
+    /// down.
+    pub fn time_to_change(&self) -> Duration {
         if (self.deployed.len() as u8) < self.max_count {
-            self.deploy();
+            return Duration::ZERO;
         }
         self.deployed
-            .retain_mut(|apple| !apple.timer_update_check(delta));
+            .iter()
+            .map(|apple| {
+                let remaining = apple.timer.remaining();
+                match remaining.subsec_nanos() {
+                    0 => remaining.min(Duration::from_secs(1)),
+                    nanos => Duration::from_nanos(nanos as u64),
+                }
+            })
+            .min()
+            .unwrap_or(Duration::MAX)
     }
     pub fn eat_apples_if<F>(&mut self, mut condition: F)
     where
-        F: FnMut(&Point) -> bool,
+        F: FnMut(&Point, AppleKind) -> bool,
     {
-        self.deployed.retain(|p| !condition(&p.get_position()));
-    }
-    fn deploy(&mut self) {
-        let mut rng = thread_rng();
-        let rand_x = rng.gen_range(0..NUM_COLS);
-        //Note: start from 1 to avoid topbar spam, temporary solution for now
-        let rand_y = rng.gen_range(1..NUM_ROWS);
-        let apple = Apple::new(Point::new(rand_x, rand_y));
+        self.deployed
+            .retain(|apple| !condition(&apple.get_position(), apple.kind));
+    }
+    fn deploy(&mut self) -> Option<Apple> {
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(self.spawned));
+        self.spawned += 1;
+        let size = self.level.size;
+        let free: Vec<Point> = (0..size.rows)
+            .flat_map(|y| (0..size.cols).map(move |x| Point::new(x, y)))
+            .filter(|place| !self.level.is_wall(*place))
+            .collect();
+        if free.is_empty() {
+            return None;
+        }
+        let place = free[rng.gen_range(0..free.len())];
+        let apple = Apple::new(place, AppleKind::Regular, &mut rng);
         self.deployed.push(apple);
+        Some(apple)
     }
 }
 
@@ -82,3 +178,67 @@ impl Drawable for AppleDispencer {
         self.deployed.iter().for_each(|apple| apple.draw(frame));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+
+    fn places(dispencer: &AppleDispencer) -> Vec<Point> {
+        dispencer.deployed.iter().map(|apple| apple.place).collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_apples() {
+        let mut events = EventQueue::new();
+        let mut first = AppleDispencer::with_seed(3, 7, Level::default());
+        let mut second = AppleDispencer::with_seed(3, 7, Level::default());
+        for _ in 0..3 {
+            first.update(Duration::ZERO, &mut events);
+            second.update(Duration::ZERO, &mut events);
+        }
+        assert_eq!(places(&first), places(&second));
+        assert_eq!(first.seed(), 7);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut events = EventQueue::new();
+        let mut dispencer = AppleDispencer::with_seed(2, 1, Level::default());
+        assert_eq!(dispencer.nearest(Point::new(0, 0)), None);
+        dispencer.update(Duration::ZERO, &mut events);
+        dispencer.update(Duration::ZERO, &mut events);
+        for place in places(&dispencer) {
+            let (nearest, left) = dispencer.nearest(place).unwrap();
+            assert_eq!(nearest, place);
+            assert!(left >= Duration::from_secs(MIN_APPEAR_TIME as u64));
+        }
+    }
+
+    #[test]
+    fn test_no_apples_on_walls() {
+        let mut level = Level::empty(Size::new(10, 10));
+        for y in 0..10 {
+            for x in 1..10 {
+                level.toggle_wall(Point::new(x, y));
+            }
+        }
+        let mut events = EventQueue::new();
+        let mut dispencer = AppleDispencer::with_seed(3, 5, level);
+        for _ in 0..3 {
+            dispencer.update(Duration::ZERO, &mut events);
+        }
+        assert!(places(&dispencer).iter().all(|place| place.x == 0));
+    }
+
+    #[test]
+    fn test_time_to_change() {
+        let mut events = EventQueue::new();
+        let mut dispencer = AppleDispencer::with_seed(1, 5, Level::default());
+        assert_eq!(dispencer.time_to_change(), Duration::ZERO, "Placed next.");
+        dispencer.update(Duration::ZERO, &mut events);
+        assert_eq!(dispencer.time_to_change(), Duration::from_secs(1));
+        dispencer.update(Duration::from_millis(300), &mut events);
+        assert_eq!(dispencer.time_to_change(), Duration::from_millis(700));
+    }
+}
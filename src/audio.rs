@@ -1,20 +1,67 @@
+//! This simple audio system took basic idea from rust_audio crate, but
+//! instead of queueing sounds into few sinks each sound gets own voice
+//! from the mixer, so it is heard immediately. Music plays in its own
+//! looping channel. Voices come from `AudioBackend`, usually rodio.
+//!
+//! Default sounds are compiled into the binary, so the game sounds the
+//! same from any working directory. Files from an override directory
+//! (and its `music` subdirectory) replace built-in ones by name.
+
+use crate::audio_backend::{AudioBackend, RodioBackend, SilentBackend};
+use crate::events::{GameEvent, Listener};
+use crate::mixer::{Mixer, Priority, Voice};
+use crate::music::Music;
+use crate::sound_pack::{SoundEvent, SoundPack};
 use log::{error, info};
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use rodio::{Decoder, Source};
 use std::collections::HashMap;
-use std::fmt::Display;
+use std::error::Error;
 use std::fs;
-use std::io::BufReader;
+use std::io::{BufReader, Cursor};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAX_VOICES: usize = 8;
+const MUSIC_DIR: &str = "music";
+const SOUND_EXTENSIONS: [&str; 4] = ["wav", "ogg", "mp3", "flac"];
+const DEFAULT_DUCK_TIME: Duration = Duration::from_secs(1);
 
-/// This simple audio system with adjustable amount of sinks(channels),
-/// took basic idea from rust_audio crate.
-const MAX_CHANNELS: usize = 4;
+const BUILTIN_SOUNDS: [(&str, &[u8]); 5] = [
+    ("enter", include_bytes!("../audio/enter.wav")),
+    ("hrum", include_bytes!("../audio/hrum.wav")),
+    ("lose_sound", include_bytes!("../audio/lose_sound.wav")),
+    ("move", include_bytes!("../audio/move.wav")),
+    ("niam", include_bytes!("../audio/niam.wav")),
+];
+const BUILTIN_MUSIC: [(&str, &[u8]); 2] = [
+    ("game", include_bytes!("../audio/music/game.wav")),
+    ("menu", include_bytes!("../audio/music/menu.wav")),
+];
+
+pub type Sound = Box<dyn Source<Item = i16> + Send>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TrackData {
+    Builtin(&'static [u8]),
+    File(PathBuf),
+}
+
+impl TrackData {
+    pub fn decode(&self) -> Result<Sound, Box<dyn Error>> {
+        Ok(match self {
+            TrackData::Builtin(bytes) => Box::new(Decoder::new(Cursor::new(*bytes))?),
+            TrackData::File(path) => Box::new(Decoder::new(BufReader::new(fs::File::open(path)?))?),
+        })
+    }
+}
 
 pub struct Audio {
-    tracks: HashMap<String, PathBuf>,
-    sinks: Vec<Sink>,
-    current: usize,
-    stream: Option<(OutputStream, OutputStreamHandle)>,
+    tracks: HashMap<String, TrackData>,
+    music_tracks: HashMap<String, TrackData>,
+    mixer: Mixer<Box<dyn Voice>>,
+    music: Music<Box<dyn Voice>>,
+    backend: Box<dyn AudioBackend>,
+    pack: SoundPack,
 }
 
 impl Default for Audio {
@@ -24,137 +71,259 @@ impl Default for Audio {
 }
 
 impl Audio {
+    /// Plays through the default device, or silently if there is none.
     pub fn new() -> Self {
-        if let Ok(stream) = OutputStream::try_default() {
-            let mut sinks: Vec<Sink> = Vec::new();
-            for _ in 0..MAX_CHANNELS {
-                let sink = Sink::try_new(&stream.1)
-                    .unwrap_or_else(|_| panic!("Can't create audio channel."));
-                sinks.push(sink);
-            }
-            Self {
-                tracks: HashMap::new(),
-                sinks,
-                stream: Some(stream),
-                current: 0,
-            }
-        } else {
-            Self {
-                tracks: HashMap::new(),
-                sinks: Vec::new(),
-                current: 0,
-                stream: None,
+        match RodioBackend::try_default() {
+            Some(backend) => Self::with_backend(backend),
+            None => {
+                info!("No audio device found, playing without sound.");
+                Self::with_backend(SilentBackend)
             }
         }
     }
 
-    fn disabled(&self) -> bool {
-        self.stream.is_none()
+    pub fn with_backend<B: AudioBackend + 'static>(backend: B) -> Self {
+        let builtin = |list: &[(&str, &'static [u8])]| {
+            list.iter()
+                .map(|(name, bytes)| (String::from(*name), TrackData::Builtin(bytes)))
+                .collect()
+        };
+        Self {
+            tracks: builtin(&BUILTIN_SOUNDS),
+            music_tracks: builtin(&BUILTIN_MUSIC),
+            mixer: Mixer::new(MAX_VOICES),
+            music: Music::new(),
+            backend: Box::new(backend),
+            pack: SoundPack::builtin(),
+        }
     }
 
-    pub fn init<P: AsRef<Path> + Display>(&mut self, audio_dir: P) {
-        if self.disabled() {
-            return;
-        }
-        info!("Loading all audio files...");
+    /// Loads files from override directory on top of built-in sounds.
+    pub fn init<P: AsRef<Path>>(&mut self, audio_dir: P) {
+        let audio_dir = audio_dir.as_ref();
+        info!("Loading audio files from {}...", audio_dir.display());
 
-        match fs::read_dir(&audio_dir) {
-            Ok(read_dir) => read_dir.for_each(|entry| {
-                let entry = entry.unwrap();
+        match fs::read_dir(audio_dir) {
+            Ok(read_dir) => read_dir.filter_map(|entry| entry.ok()).for_each(|entry| {
                 let path: PathBuf = entry.path();
                 if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_file() {
+                    if file_type.is_file() && is_sound_file(&path) {
                         self.add(path);
                     }
                 }
             }),
-            Err(e) => error!("{}", format!("{}: {}", e.to_string(), audio_dir)),
+            Err(e) => error!("{}: {}", e, audio_dir.display()),
         };
+
+        let music_dir = audio_dir.join(MUSIC_DIR);
+        if let Ok(read_dir) = fs::read_dir(&music_dir) {
+            read_dir
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_file() && is_sound_file(&entry.path()))
+                .for_each(|entry| self.add_music(entry.path()));
+        }
+    }
+
+    /// Loads sound pack directory: its files and the manifest for events.
+    pub fn load_pack<P: AsRef<Path>>(&mut self, dir: P) {
+        match SoundPack::load(&dir) {
+            Ok(pack) => {
+                info!("Using sound pack {}", pack.name());
+                self.init(&dir);
+                self.pack = pack;
+            }
+            Err(e) => error!("Can't load sound pack {}: {}", dir.as_ref().display(), e),
+        }
     }
 
     fn add(&mut self, path: PathBuf) {
-        let string = String::from(path.file_stem().unwrap().to_str().unwrap());
-        self.tracks.insert(string, path);
+        if let Some(name) = track_name(&path) {
+            self.tracks.insert(name, TrackData::File(path));
+        }
+    }
+
+    fn add_music(&mut self, path: PathBuf) {
+        if let Some(name) = track_name(&path) {
+            self.music_tracks.insert(name, TrackData::File(path));
+        }
     }
 
-    pub fn play<S: AsRef<str>>(&mut self, name: S) {
-        if self.disabled() {
+    /// Names of all music tracks found, sorted to show them in menu.
+    pub fn music_tracks(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.music_tracks.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Plays what current sound pack has for the game event.
+    pub fn play_event(&mut self, event: SoundEvent) {
+        let Some((name, gain)) = self.pack.pick(event) else {
             return;
+        };
+        if event.ducks_music() {
+            self.duck_under(&name);
         }
-        let path = match self.tracks.get(name.as_ref()) {
+        self.play_with_gain(name, event.priority(), gain);
+    }
+
+    pub fn play<S: AsRef<str>>(&mut self, name: S, priority: Priority) {
+        self.play_with_gain(name, priority, 1.0);
+    }
+
+    fn play_with_gain<S: AsRef<str>>(&mut self, name: S, priority: Priority, gain: f32) {
+        let track = match self.tracks.get(name.as_ref()) {
             Some(val) => val,
             None => {
-                error!(
-                    "{}",
-                    format!("No audio file with such name: {}.", name.as_ref())
-                );
+                error!("No audio file with such name: {}.", name.as_ref());
                 return;
             }
         };
 
-        let file = BufReader::new(fs::File::open(path).unwrap());
-        let source = Decoder::new(file).unwrap();
-        let current_sink = &self.sinks[self.current];
-
-        self.current += 1;
-        if self.current >= MAX_CHANNELS {
-            self.current = 0
+        if !self.mixer.make_room(priority) {
+            return;
+        }
+        if let Some(voice) = self.backend.start(name.as_ref(), track, false) {
+            self.mixer.add(voice, priority, gain);
         }
+    }
 
-        current_sink.append(source);
-        current_sink.play();
+    /// Ducks music under the effect for the length of the effect.
+    fn duck_under(&mut self, name: &str) {
+        let duration = self
+            .tracks
+            .get(name)
+            .and_then(|track| track.decode().ok())
+            .and_then(|source| source.total_duration())
+            .unwrap_or(DEFAULT_DUCK_TIME);
+        self.music.duck(duration);
     }
 
-    pub fn wait(&self) {
-        if self.disabled() {
+    /// Crossfades into looping music track, does nothing if it already plays.
+    pub fn play_music<S: AsRef<str>>(&mut self, name: S) {
+        if self.music.current() == Some(name.as_ref()) {
             return;
         }
-        loop {
-            if self.sinks.iter().any(|i| !i.empty()) {
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                continue;
+        let track = match self.music_tracks.get(name.as_ref()) {
+            Some(val) => val,
+            None => {
+                error!("No music file with such name: {}.", name.as_ref());
+                return;
             }
-            break;
+        };
+        match self.backend.start(name.as_ref(), track, true) {
+            Some(voice) => self.music.switch(name.as_ref(), voice),
+            None => self.music.fade_out(),
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music.fade_out();
+    }
+
+    /// Sets volume for effects and music, applied to playing sounds as well.
+    pub fn set_volume(&mut self, sfx: f32, music: f32) {
+        self.mixer.set_volume(sfx);
+        self.music.set_volume(music);
+    }
+
+    /// Moves music fades forward, should be called every loop.
+    pub fn update(&mut self, delta: Duration) {
+        self.music.update(delta);
+    }
+
+    /// Music is fading, it needs updates more often than the game does.
+    pub fn is_fading(&self) -> bool {
+        self.music.is_fading()
+    }
+
+    pub fn wait(&self) {
+        while !self.mixer.is_idle() {
+            std::thread::sleep(std::time::Duration::from_millis(50));
         }
     }
 }
 
+impl Listener for Audio {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Moved { .. } => self.play_event(SoundEvent::Move),
+            GameEvent::AteApple { kind, .. } => self.play_event(SoundEvent::Eat(*kind)),
+            GameEvent::Died { .. } => self.play_event(SoundEvent::Die),
+            _ => {}
+        }
+    }
+}
+
+/// Track is named by its file without extension. Names which aren't
+/// valid UTF-8 get replacement characters, they just won't match any event.
+fn track_name(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+fn is_sound_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SOUND_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio_backend::RecordingBackend;
     use std::time::Instant;
     const AUDIO_FILE: &str = "enter";
 
     #[test]
     fn test_audio_new() {
-        let audio = Audio::new();
+        let mut audio = Audio::with_backend(SilentBackend);
         assert_eq!(
-            audio.sinks.len(),
-            MAX_CHANNELS,
-            "Sinks same number as channels defined."
+            audio.mixer.max_voices(),
+            MAX_VOICES,
+            "Mixer has same number of voices as defined."
         );
-        assert!(audio.stream.is_some(), "Audio device connected.");
-        assert!(!audio.disabled(), "Disabled return false.");
+        assert!(audio.mixer.is_idle(), "Nothing plays at start.");
+        audio.play(AUDIO_FILE, Priority::Normal);
+        assert!(audio.mixer.is_idle(), "Silent backend plays nothing.");
+    }
+
+    #[test]
+    fn test_builtin_tracks() {
+        let audio = Audio::with_backend(SilentBackend);
+        assert_eq!(audio.tracks.len(), BUILTIN_SOUNDS.len());
+        assert_eq!(audio.music_tracks(), vec!["game", "menu"]);
+        for track in audio.tracks.values().chain(audio.music_tracks.values()) {
+            assert!(track.decode().is_ok(), "Built-in sounds can be decoded.");
+        }
     }
 
     #[test]
     fn test_audio_initialization_and_add() {
-        let mut audio = Audio::new();
+        let mut audio = Audio::with_backend(SilentBackend);
         audio.add(PathBuf::from(format!("audio/{}.wav", AUDIO_FILE)));
-        assert_eq!(audio.tracks.len(), 1, "Have correct number of tracks");
+        assert_eq!(
+            audio.tracks.len(),
+            BUILTIN_SOUNDS.len(),
+            "File replaces built-in track with same name."
+        );
 
         let check_win_exist = |audio: &mut Audio| {
-            let str_path = audio.tracks.get(AUDIO_FILE).unwrap().to_str();
             assert_eq!(
-                str_path,
-                Some(format!("audio/{}.wav", AUDIO_FILE)).as_deref(),
+                audio.tracks.get(AUDIO_FILE),
+                Some(&TrackData::File(PathBuf::from(format!(
+                    "audio/{}.wav",
+                    AUDIO_FILE
+                )))),
                 "Check if we can get path to file by name."
             );
         };
         check_win_exist(&mut audio);
         audio.tracks.clear();
 
-        let files_count = fs::read_dir("audio").unwrap().count();
+        let files_count = fs::read_dir("audio")
+            .unwrap()
+            .filter(|entry| is_sound_file(&entry.as_ref().unwrap().path()))
+            .count();
         audio.init("audio");
         assert_eq!(
             audio.tracks.len(),
@@ -162,34 +331,157 @@ mod tests {
             "Files in audio dir same as tracks."
         );
         check_win_exist(&mut audio);
+        assert_eq!(
+            audio.music_tracks.get("menu"),
+            Some(&TrackData::File(PathBuf::from("audio/music/menu.wav"))),
+            "Music loaded from music subdirectory."
+        );
     }
 
     #[test]
     fn test_play() {
-        let mut audio = Audio::new();
-        audio.init("audio");
-        assert!(audio.sinks[0].empty(), "First sink is empty at start.");
-        audio.play(AUDIO_FILE);
-        assert!(
-            !audio.sinks[0].empty(),
-            "After first play, first sink have song."
-        );
-        assert_eq!(audio.current, 1, "Current sink changes to second.");
-        for _ in 1..MAX_CHANNELS {
-            audio.play(AUDIO_FILE);
+        let backend = RecordingBackend::new();
+        let recording = backend.recording();
+        let mut audio = Audio::with_backend(backend);
+        audio.play(AUDIO_FILE, Priority::Normal);
+        assert_eq!(audio.mixer.active(), 1, "After first play, one voice busy.");
+        assert_eq!(recording.names(), vec![AUDIO_FILE]);
+
+        for _ in 0..MAX_VOICES {
+            audio.play("move", Priority::Low);
         }
         assert_eq!(
-            audio.current, 0,
-            "After play from all channels, current become 0."
+            audio.mixer.active(),
+            MAX_VOICES,
+            "Sounds over the limit steal voices instead of queueing."
+        );
+        let played = recording.played();
+        assert!(!played[0].is_stopped(), "Important sound kept.");
+        assert!(played[1].is_stopped(), "Oldest move sound stolen.");
+
+        audio.play("no_such_sound", Priority::High);
+        assert_eq!(recording.played().len(), MAX_VOICES + 1);
+    }
+
+    #[test]
+    fn test_music_and_volume() {
+        let backend = RecordingBackend::new();
+        let recording = backend.recording();
+        let mut audio = Audio::with_backend(backend);
+        audio.set_volume(0.5, 0.25);
+        audio.play_music("menu");
+        audio.play_music("menu");
+        audio.update(Duration::from_secs(5));
+        audio.play("move", Priority::Low);
+
+        let played = recording.played();
+        assert_eq!(
+            recording.names(),
+            vec!["menu", "move"],
+            "Music started once."
         );
+        assert!(played[0].looping && !played[1].looping);
+        assert_eq!(played[0].volume(), 0.25);
+        assert_eq!(played[1].volume(), 0.5);
+
+        audio.set_volume(0.0, 0.0);
+        assert_eq!(played[0].volume(), 0.0, "Volume changes live.");
+        assert_eq!(played[1].volume(), 0.0);
+    }
+
+    #[test]
+    fn test_play_event() {
+        let backend = RecordingBackend::new();
+        let recording = backend.recording();
+        let mut audio = Audio::with_backend(backend);
+        audio.play_event(SoundEvent::Start);
+        audio.play_event(SoundEvent::MenuNavigate);
+        audio.play_event(SoundEvent::Die);
+        assert_eq!(recording.names(), vec!["enter", "move", "lose_sound"]);
+        assert_eq!(recording.played()[1].volume(), 0.5, "Pack volume used.");
+    }
+
+    #[test]
+    fn test_game_events() {
+        use crate::{apple::AppleKind, events::DeathCause, events::EventQueue, Point};
+        let backend = RecordingBackend::new();
+        let recording = backend.recording();
+        let mut audio = Audio::with_backend(backend);
+        let mut events = EventQueue::new();
+        events.push(GameEvent::Moved {
+            head: Point::new(1, 1),
+        });
+        events.push(GameEvent::AppleExpired {
+            pos: Point::new(5, 5),
+        });
+        events.push(GameEvent::AteApple {
+            kind: AppleKind::Regular,
+            pos: Point::new(1, 1),
+        });
+        events.push(GameEvent::Died {
+            cause: DeathCause::Tail,
+        });
+        events.dispatch(&mut [&mut audio]);
+        let names = recording.names();
+        assert_eq!(names.len(), 3, "Silent events make no sound.");
+        assert_eq!(names[0], "move");
+        assert!(names[1] == "niam" || names[1] == "hrum");
+        assert_eq!(names[2], "lose_sound");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_odd_file_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = std::env::temp_dir().join(format!("snake_names_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(
+            "audio/niam.wav",
+            dir.join(OsStr::from_bytes(b"bad\xff.wav")),
+        )
+        .unwrap();
+        fs::copy("audio/niam.wav", dir.join("die.wav")).unwrap();
+
+        let mut audio = Audio::with_backend(SilentBackend);
+        audio.tracks.clear();
+        audio.init(&dir);
+        assert_eq!(audio.tracks.len(), 2);
+        assert!(audio.tracks.contains_key("bad\u{fffd}"));
+        assert!(audio.tracks.contains_key("die"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_pack() {
+        let dir = std::env::temp_dir().join(format!("snake_pack_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("audio/niam.wav", dir.join("boom.wav")).unwrap();
+        fs::write(
+            dir.join("pack.json"),
+            r#"{ "name": "test", "events": { "die": { "sounds": ["boom.wav"] } } }"#,
+        )
+        .unwrap();
+
+        let backend = RecordingBackend::new();
+        let recording = backend.recording();
+        let mut audio = Audio::with_backend(backend);
+        audio.load_pack(&dir);
+        audio.play_event(SoundEvent::Die);
+        audio.play_event(SoundEvent::Move);
+        assert_eq!(recording.names(), vec!["boom"], "Only pack sounds play.");
+        assert!(
+            !audio.tracks.contains_key("pack"),
+            "Manifest is not a sound."
+        );
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
     fn test_wait() {
-        let mut audio = Audio::new();
-        audio.init("audio");
+        let mut audio = Audio::with_backend(RecordingBackend::new());
         let start = Instant::now();
-        audio.play(AUDIO_FILE);
+        audio.play(AUDIO_FILE, Priority::Normal);
         audio.wait();
         let duration = start.elapsed();
         assert!(duration.as_millis() > 200);
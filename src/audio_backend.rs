@@ -0,0 +1,187 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use log::error;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+
+use crate::{audio::TrackData, mixer::Voice};
+
+/// `Audio` decides what and when should sound, backend only starts voices.
+/// Besides the real device there is a silent backend for machines without
+/// sound card and a recording one, which remembers what was requested, so
+/// tests can check sounds of the game without any hardware.
+pub trait AudioBackend {
+    /// Starts the track right away, returns `None` if nothing will be heard.
+    fn start(&mut self, name: &str, track: &TrackData, looping: bool) -> Option<Box<dyn Voice>>;
+}
+
+pub struct RodioBackend {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl RodioBackend {
+    pub fn try_default() -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+        Some(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn start(&mut self, name: &str, track: &TrackData, looping: bool) -> Option<Box<dyn Voice>> {
+        let source = match track.decode() {
+            Ok(source) => source,
+            Err(e) => {
+                error!("Can't decode sound {}: {}", name, e);
+                return None;
+            }
+        };
+        let sink = match Sink::try_new(&self.handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                error!("Can't create audio channel: {}", e);
+                return None;
+            }
+        };
+        if looping {
+            sink.append(source.buffered().repeat_infinite());
+        } else {
+            sink.append(source);
+        }
+        Some(Box::new(sink))
+    }
+}
+
+pub struct SilentBackend;
+
+impl AudioBackend for SilentBackend {
+    fn start(&mut self, _name: &str, _track: &TrackData, _looping: bool) -> Option<Box<dyn Voice>> {
+        None
+    }
+}
+
+#[derive(Default)]
+struct VoiceState {
+    stopped: Cell<bool>,
+    volume: Cell<f32>,
+}
+
+/// One sound requested from the recording backend.
+#[derive(Clone)]
+pub struct Played {
+    pub name: String,
+    /// Time since the backend was created.
+    pub at: Duration,
+    pub looping: bool,
+    state: Rc<VoiceState>,
+}
+
+impl Played {
+    pub fn volume(&self) -> f32 {
+        self.state.volume.get()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state.stopped.get()
+    }
+}
+
+/// Shared log of the recording backend, stays with the test after
+/// the backend itself is moved into `Audio`.
+#[derive(Clone, Default)]
+pub struct Recording {
+    played: Rc<RefCell<Vec<Played>>>,
+}
+
+impl Recording {
+    pub fn played(&self) -> Vec<Played> {
+        self.played.borrow().clone()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.played
+            .borrow()
+            .iter()
+            .map(|played| played.name.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.played.borrow_mut().clear();
+    }
+}
+
+pub struct RecordingBackend {
+    created: Instant,
+    recording: Recording,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            recording: Recording::default(),
+        }
+    }
+
+    pub fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+}
+
+impl AudioBackend for RecordingBackend {
+    fn start(&mut self, name: &str, track: &TrackData, looping: bool) -> Option<Box<dyn Voice>> {
+        let state = Rc::new(VoiceState::default());
+        state.volume.set(1.0);
+        self.recording.played.borrow_mut().push(Played {
+            name: String::from(name),
+            at: self.created.elapsed(),
+            looping,
+            state: state.clone(),
+        });
+        // Recorded voice lasts as long as the real sound would.
+        let ends = match looping {
+            true => None,
+            false => {
+                let duration = track
+                    .decode()
+                    .ok()
+                    .and_then(|source| source.total_duration())
+                    .unwrap_or_default();
+                Some(Instant::now() + duration)
+            }
+        };
+        Some(Box::new(RecordedVoice { state, ends }))
+    }
+}
+
+struct RecordedVoice {
+    state: Rc<VoiceState>,
+    ends: Option<Instant>,
+}
+
+impl Voice for RecordedVoice {
+    fn is_finished(&self) -> bool {
+        self.state.stopped.get() || self.ends.is_some_and(|ends| Instant::now() >= ends)
+    }
+
+    fn stop(&self) {
+        self.state.stopped.set(true);
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.state.volume.set(volume);
+    }
+}
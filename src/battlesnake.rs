@@ -0,0 +1,328 @@
+use std::{io, time::Duration};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use ureq::{
+    http::{Response, Uri},
+    Agent, Body,
+};
+
+use crate::{
+    bot::{parse_move, Controller, GameState},
+    snake::Direction,
+    Point,
+};
+
+/// Snake server speaking the Battlesnake API (<https://docs.battlesnake.com/api>),
+/// so bots written for it can play here. The game is the host: it POSTs
+/// `/start` before the first move, `/move` for every move and `/end` when
+/// the game is over, the server answers moves with `{"move": "up"}`.
+///
+/// Battlesnake counts `y` from the bottom of the board, here it's counted
+/// from the top, so rows are flipped both ways and `up` means the same.
+/// There are no walls in Battlesnake, walls of the level are sent as
+/// hazards. The game has one snake, so one server plays it.
+pub struct Battlesnake {
+    /// Without the trailing slash, endpoints are added to it.
+    url: String,
+    agent: Agent,
+    game_id: String,
+    timeout: Duration,
+    started: bool,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+struct Coord {
+    x: usize,
+    y: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct Ruleset {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct Game<'a> {
+    id: &'a str,
+    ruleset: Ruleset,
+    map: &'static str,
+    timeout: u64,
+    source: &'static str,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct Customizations {
+    color: &'static str,
+    head: &'static str,
+    tail: &'static str,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct BattleSnake {
+    id: String,
+    name: &'static str,
+    health: u32,
+    body: Vec<Coord>,
+    latency: &'static str,
+    head: Coord,
+    length: usize,
+    shout: &'static str,
+    squad: &'static str,
+    customizations: Customizations,
+}
+
+#[derive(Serialize, Debug)]
+struct Board {
+    height: usize,
+    width: usize,
+    food: Vec<Coord>,
+    hazards: Vec<Coord>,
+    snakes: Vec<BattleSnake>,
+}
+
+/// Body of every request to the snake server.
+#[derive(Serialize, Debug)]
+struct Request<'a> {
+    game: Game<'a>,
+    turn: u64,
+    board: Board,
+    you: BattleSnake,
+}
+
+#[derive(Deserialize)]
+struct MoveResponse {
+    #[serde(rename = "move")]
+    direction: String,
+}
+
+impl<'a> Request<'a> {
+    fn new(game_id: &'a str, timeout: Duration, state: &GameState) -> Self {
+        let flip = |point: &Point| Coord {
+            x: point.x,
+            y: state.height - 1 - point.y,
+        };
+        let snakes: Vec<BattleSnake> = state
+            .snakes
+            .iter()
+            .enumerate()
+            .map(|(index, snake)| BattleSnake {
+                id: format!("snake-{}", index),
+                name: "snake",
+                // No hunger in this game, dead snake is out of health
+                health: if snake.alive { 100 } else { 0 },
+                body: snake.body.iter().map(flip).collect(),
+                latency: "0",
+                head: flip(&snake.body[0]),
+                length: snake.body.len(),
+                shout: "",
+                squad: "",
+                customizations: Customizations {
+                    color: "#00ff00",
+                    head: "default",
+                    tail: "default",
+                },
+            })
+            .collect();
+        let you = snakes[state.you].clone();
+        Self {
+            game: Game {
+                id: game_id,
+                ruleset: Ruleset {
+                    name: if snakes.len() == 1 {
+                        "solo"
+                    } else {
+                        "standard"
+                    },
+                    version: "v1.0.0",
+                },
+                map: "standard",
+                timeout: timeout.as_millis() as u64,
+                source: "custom",
+            },
+            turn: state.tick,
+            board: Board {
+                height: state.height,
+                width: state.width,
+                food: state
+                    .apples
+                    .iter()
+                    .map(|apple| flip(&apple.position))
+                    .collect(),
+                hazards: state.walls.iter().map(flip).collect(),
+                snakes,
+            },
+            you,
+        }
+    }
+}
+
+impl Battlesnake {
+    /// Url of the snake server, `http://` or `https://`.
+    pub fn new(url: &str, timeout: Duration) -> io::Result<Self> {
+        let invalid = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} in url '{}'", reason, url),
+            )
+        };
+        let uri: Uri = url.parse().map_err(|_| invalid("bad url"))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(invalid("only http:// and https:// are supported"));
+        }
+        // Port after the host, but not inside of an IPv6 address
+        let authority = uri.authority().map_or("", |authority| authority.as_str());
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        let port = host.rsplit_once(':').map(|(_, port)| port);
+        if port.is_some_and(|port| !port.ends_with(']') && port.parse::<u16>().is_err()) {
+            return Err(invalid("bad port"));
+        }
+        let agent = Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .build()
+            .new_agent();
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            agent,
+            game_id: format!("snake-{:016x}", rand::random::<u64>()),
+            timeout,
+            started: false,
+        })
+    }
+
+    fn post(&self, endpoint: &str, state: &GameState) -> Result<Response<Body>, ureq::Error> {
+        let request = Request::new(&self.game_id, self.timeout, state);
+        self.agent
+            .post(format!("{}/{}", self.url, endpoint))
+            .send_json(&request)
+    }
+}
+
+impl Controller for Battlesnake {
+    fn ask(&mut self, state: &GameState) -> Option<Direction> {
+        if !self.started {
+            self.started = true;
+            info!("Battlesnake game {} started", self.game_id);
+            if let Err(e) = self.post("start", state) {
+                error!("Battlesnake /start failed: {}", e);
+            }
+        }
+        let response = self
+            .post("move", state)
+            .and_then(|mut response| response.body_mut().read_json::<MoveResponse>());
+        match response {
+            Ok(MoveResponse { direction }) => {
+                let parsed = parse_move(&direction);
+                if parsed.is_none() {
+                    error!("Battlesnake sent unknown move: {}", direction);
+                }
+                parsed
+            }
+            Err(ureq::Error::Json(e)) => {
+                error!("Battlesnake sent bad /move response: {}", e);
+                None
+            }
+            Err(e) => {
+                info!("Battlesnake /move on turn {} failed: {}", state.tick, e);
+                None
+            }
+        }
+    }
+
+    fn finish(&mut self, state: &GameState) {
+        if let Err(e) = self.post("end", state) {
+            error!("Battlesnake /end failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apple::AppleDispencer, snake::Snake, topbar::TopBar};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    fn state() -> GameState {
+        GameState::new(3, &Snake::new(100), &AppleDispencer::new(1), &TopBar::new())
+    }
+
+    #[test]
+    fn test_url() {
+        let timeout = Duration::from_millis(500);
+        let snake = Battlesnake::new("http://127.0.0.1:8000/snake/", timeout).unwrap();
+        assert_eq!(snake.url, "http://127.0.0.1:8000/snake");
+        assert!(Battlesnake::new("https://play.battlesnake.com", timeout).is_ok());
+        assert!(Battlesnake::new("ftp://example.com", timeout).is_err());
+        assert!(Battlesnake::new("http://host:port", timeout).is_err());
+        assert!(Battlesnake::new("http://[::1]:8000", timeout).is_ok());
+    }
+
+    #[test]
+    fn test_request() {
+        let json =
+            serde_json::to_value(Request::new("id", Duration::from_millis(500), &state())).unwrap();
+        assert_eq!(json["turn"], 3);
+        assert_eq!(json["game"]["timeout"], 500);
+        assert_eq!(json["board"]["width"], 30);
+        // Head is at 20,20 counted from the top
+        assert_eq!(json["you"]["head"], serde_json::json!({"x": 20, "y": 9}));
+        assert_eq!(json["board"]["snakes"][0]["head"], json["you"]["head"]);
+        assert_eq!(json["you"]["health"], 100);
+    }
+
+    // Snake server on loopback which goes left and records requested paths.
+    #[test]
+    fn test_stub_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut paths = Vec::new();
+            for stream in listener.incoming().take(4) {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                paths.push(line.split_whitespace().nth(1).unwrap().to_string());
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(request["board"]["height"], 30);
+                let answer = r#"{"move":"left","shout":"hi"}"#;
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    answer.len(),
+                    answer
+                )
+                .unwrap();
+            }
+            paths
+        });
+        let url = format!("http://127.0.0.1:{}/bot/", port);
+        let mut snake = Battlesnake::new(&url, Duration::from_secs(5)).unwrap();
+        assert_eq!(snake.ask(&state()), Some(Direction::Left));
+        assert_eq!(snake.ask(&state()), Some(Direction::Left));
+        snake.finish(&state());
+        assert_eq!(
+            server.join().unwrap(),
+            ["/bot/start", "/bot/move", "/bot/move", "/bot/end"]
+        );
+    }
+}
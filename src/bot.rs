@@ -0,0 +1,224 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use log::{error, info};
+use serde::Serialize;
+
+use crate::{
+    apple::AppleDispencer,
+    snake::{Direction, Snake},
+    topbar::TopBar,
+    Point,
+};
+
+/// Anything playing instead of the keyboard.
+pub trait Controller {
+    /// Move for the state, `None` keeps the snake going straight.
+    fn ask(&mut self, state: &GameState) -> Option<Direction>;
+    /// Last state of the game, no move is expected.
+    fn finish(&mut self, state: &GameState);
+}
+
+/// Snake controlled by another program. Before every move of the snake
+/// the game writes its state as one line of JSON to the bot's stdin and
+/// waits for one line with the move: `left`, `up`, `right` or `down`.
+/// Anything else, or no answer in time, keeps the snake going straight.
+/// When the game is over the last state is sent with `alive: false` and
+/// stdin is closed.
+pub struct Bot {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    moves: Receiver<String>,
+    timeout: Duration,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct SnakeState {
+    /// Head first.
+    pub body: Vec<Point>,
+    pub direction: &'static str,
+    pub alive: bool,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct AppleState {
+    pub position: Point,
+    pub kind: &'static str,
+    pub expires_in_ms: u64,
+}
+
+/// What the bot gets each move. Snakes are a list, the one controlled by
+/// the bot is `you`.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct GameState {
+    pub tick: u64,
+    pub width: usize,
+    pub height: usize,
+    pub walls: Vec<Point>,
+    pub snakes: Vec<SnakeState>,
+    pub you: usize,
+    pub apples: Vec<AppleState>,
+    pub score: usize,
+}
+
+impl GameState {
+    pub fn new(tick: u64, snake: &Snake, apples: &AppleDispencer, topbar: &TopBar) -> Self {
+        let level = snake.level();
+        Self {
+            tick,
+            width: level.size.cols,
+            height: level.size.rows,
+            walls: level.walls.iter().copied().collect(),
+            snakes: vec![SnakeState {
+                body: snake.body().to_vec(),
+                direction: snake.direction().name(),
+                alive: !snake.is_dead(),
+            }],
+            you: 0,
+            apples: apples
+                .apples()
+                .map(|(position, kind, left)| AppleState {
+                    position,
+                    kind: kind.name(),
+                    expires_in_ms: left.as_millis() as u64,
+                })
+                .collect(),
+            score: topbar.get_scores(),
+        }
+    }
+}
+
+/// Move from a line of the bot, spaces and case don't matter.
+pub fn parse_move(line: &str) -> Option<Direction> {
+    Direction::from_name(line.trim())
+}
+
+impl Bot {
+    /// Command is split on whitespace, the first word is the program.
+    pub fn spawn(command: &str, timeout: Duration) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        // Bot's stderr would draw over the game
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("Stdout is piped.");
+        let (tx, moves) = mpsc::channel();
+        // Lines are read in the background, so waiting for a slow bot can
+        // be given up after timeout
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        info!("Bot started: {}", command);
+        Ok(Self {
+            child,
+            stdin,
+            moves,
+            timeout,
+        })
+    }
+
+    fn send(&mut self, state: &GameState) -> Option<()> {
+        let stdin = self.stdin.as_mut()?;
+        let line = serde_json::to_string(state).expect("State is always serializable.");
+        if let Err(e) = writeln!(stdin, "{}", line).and_then(|_| stdin.flush()) {
+            error!("Can't write to bot: {}", e);
+            self.stdin = None;
+            return None;
+        }
+        Some(())
+    }
+}
+
+impl Controller for Bot {
+    /// Sends the state and waits for the move.
+    fn ask(&mut self, state: &GameState) -> Option<Direction> {
+        // Answer which came too late was for the previous move
+        while self.moves.try_recv().is_ok() {}
+        self.send(state)?;
+        match self.moves.recv_timeout(self.timeout) {
+            Ok(line) => {
+                let direction = parse_move(&line);
+                if direction.is_none() {
+                    error!("Bot sent unknown move: {}", line);
+                }
+                direction
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                info!("Bot didn't answer on tick {} in time", state.tick);
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// The bot doesn't answer the last state, its stdin is closed.
+    fn finish(&mut self, state: &GameState) {
+        self.send(state);
+        self.stdin = None;
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        self.stdin = None;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> GameState {
+        GameState::new(3, &Snake::new(100), &AppleDispencer::new(1), &TopBar::new())
+    }
+
+    #[test]
+    fn test_state() {
+        let json = serde_json::to_value(state()).unwrap();
+        assert_eq!(json["width"], 30);
+        assert_eq!(json["snakes"][0]["body"][0]["x"], 20);
+        assert_eq!(json["snakes"][0]["direction"], "right");
+        assert_eq!(json["you"], 0);
+    }
+
+    #[test]
+    fn test_parse_move() {
+        assert_eq!(parse_move(" UP\r"), Some(Direction::Up));
+        assert_eq!(parse_move("left"), Some(Direction::Left));
+        assert_eq!(parse_move("jump"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_bot() {
+        let script = std::env::temp_dir().join(format!("snake_bot_{}.sh", std::process::id()));
+        std::fs::write(&script, "while read state; do echo down; done\n").unwrap();
+        let command = format!("sh {}", script.display());
+        let mut bot = Bot::spawn(&command, Duration::from_secs(5)).unwrap();
+        assert_eq!(bot.ask(&state()), Some(Direction::Down));
+        assert_eq!(bot.ask(&state()), Some(Direction::Down));
+        bot.finish(&state());
+        std::fs::remove_file(&script).unwrap();
+
+        let mut silent = Bot::spawn("sleep 5", Duration::from_millis(50)).unwrap();
+        assert_eq!(silent.ask(&state()), None, "No answer in time.");
+    }
+}
@@ -0,0 +1,481 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use log::LevelFilter;
+
+use crate::{
+    level::{MAX_SIZE, MIN_SIZE},
+    logger,
+    menu::{APPLE_LIMITS, SPEED_LIMITS},
+    screen::RenderMode,
+    Size,
+};
+
+pub const HELP: &str = "\
+Snake in the terminal.
+
+Usage: snake [options] [command]
+
+Without a command the main menu is opened.
+
+Commands:
+  play              Start a game right away, exit when it's over
+  replay <file>     Watch a recorded game, the last one is in
+                    the config directory as last_replay.json
+  scores            Print best scores of the profile
+  stats             Print lifetime stats of the profile
+  edit <level>      Draw walls of a level, new file is created
+                    if there is none
+  tournament <player>...  Let players play each other without the
+                    terminal and print standings. A player is a
+                    built-in AI (greedy or random), a Battlesnake
+                    url or a bot command
+  help              Print this help
+
+Play options:
+  --speed <ms>      Time of one snake move, from 100 to 700
+  --apples <n>      Apples on the board at once, from 1 to 10
+  --width <n>       Board width, from 10 to 100
+  --height <n>      Board height, from 10 to 100
+  --level <file>    Play on a level made with `edit`
+  --seed <n>        Seed for apples, same seed gives same apples
+  --mode <mode>     Cells: normal, double or half
+  --no-audio        Play without sound
+  --bot <command>   Let a program play, it gets the game state as JSON
+                    on stdin and answers with a move on stdout. The
+                    command is split on spaces, quotes are not special
+  --battlesnake <url>  Let a Battlesnake server play the only snake,
+                    the game POSTs /start, /move and /end to the url.
+                    One server per game, there are no other snakes
+  --bot-timeout <ms>  Time the bot has for a move, from 1 to 5000,
+                    50 by default, 500 for a Battlesnake server. In a
+                    game it's never longer than one move of the snake
+
+Tournament options:
+  --games <n>       Games in every match, from 1 to 1000, 10 by default
+  --swiss <rounds>  Swiss rounds instead of everyone playing everyone
+  --threads <n>     Games played at once, from 1 to 64
+  --json            Print standings as JSON instead of CSV
+  --seed, --apples, --width, --height, --level, --bot-timeout
+                    Same as for play
+
+Edit options:
+  --width <n>, --height <n>  Size of a new level
+
+Sound options, for play and the menu:
+  --audio-dir <dir>   Directory with sounds replacing built-in ones
+  --sound-pack <dir>  Sound pack directory with pack.json
+
+Options for every command:
+  --profile <name>    Profile for scores and stats
+  --log-level <level> off, error, warn, info, debug or trace, info by
+                      default or from SNAKE_LOG. Log is snake.log in
+                      the config directory
+  -h, --help          Print this help
+";
+
+/// What to do, the menu is opened when nothing is given.
+#[derive(PartialEq, Debug)]
+pub enum Command {
+    Menu,
+    Play(PlayOptions),
+    Replay(PathBuf),
+    Scores,
+    Stats,
+    Edit(PathBuf, Size),
+    Tournament(TournamentOptions),
+    Help,
+}
+
+/// Game settings from command line, what is not given comes from
+/// the menu and settings file.
+#[derive(Default, PartialEq, Debug)]
+pub struct PlayOptions {
+    pub speed: Option<u64>,
+    pub apples: Option<u8>,
+    pub size: Option<Size>,
+    pub level: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub mode: Option<RenderMode>,
+    pub no_audio: bool,
+    /// Program controlling the snake instead of the keyboard.
+    pub bot: Option<String>,
+    /// Url of a Battlesnake server controlling the snake.
+    pub battlesnake: Option<String>,
+    pub bot_timeout: Option<u64>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct TournamentOptions {
+    /// Built-in AI names, Battlesnake urls or bot commands.
+    pub players: Vec<String>,
+    pub games: usize,
+    /// Rounds of Swiss, everyone plays everyone when not given.
+    pub swiss: Option<usize>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub apples: Option<u8>,
+    pub size: Option<Size>,
+    pub level: Option<PathBuf>,
+    pub bot_timeout: Option<u64>,
+    pub json: bool,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub audio_dir: Option<PathBuf>,
+    pub sound_pack: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub log_level: Option<LevelFilter>,
+}
+
+// Options are parsed into this first, then checked against the command.
+#[derive(Default)]
+struct Parsed {
+    command: Option<String>,
+    targets: Vec<String>,
+    speed: Option<u64>,
+    apples: Option<u8>,
+    width: Option<usize>,
+    height: Option<usize>,
+    level: Option<PathBuf>,
+    seed: Option<u64>,
+    mode: Option<RenderMode>,
+    no_audio: bool,
+    bot: Option<String>,
+    battlesnake: Option<String>,
+    bot_timeout: Option<u64>,
+    games: Option<usize>,
+    swiss: Option<usize>,
+    threads: Option<usize>,
+    json: bool,
+    help: bool,
+}
+
+fn number<T>(option: &str, value: &str, (min, max): (T, T)) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + Display + Copy,
+{
+    match value.parse::<T>() {
+        Ok(n) if n >= min && n <= max => Ok(n),
+        _ => Err(format!(
+            "{} should be a number from {} to {}, got '{}'",
+            option, min, max, value
+        )),
+    }
+}
+
+/// Arguments without the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Cli, String> {
+    let mut cli = Cli {
+        command: Command::Menu,
+        audio_dir: None,
+        sound_pack: None,
+        profile: None,
+        log_level: None,
+    };
+    let mut parsed = Parsed::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            if parsed.command.is_none() {
+                parsed.command = Some(arg);
+            } else {
+                parsed.targets.push(arg);
+            }
+            continue;
+        }
+        if arg == "-h" || arg == "--help" {
+            parsed.help = true;
+            continue;
+        }
+        if arg == "--no-audio" {
+            parsed.no_audio = true;
+            continue;
+        }
+        if arg == "--json" {
+            parsed.json = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--audio-dir" => cli.audio_dir = Some(PathBuf::from(value)),
+            "--sound-pack" => cli.sound_pack = Some(PathBuf::from(value)),
+            "--profile" => cli.profile = Some(value),
+            "--log-level" => {
+                cli.log_level = Some(logger::parse_level(&value).ok_or_else(|| {
+                    format!(
+                        "--log-level should be off, error, warn, info, debug or trace, got '{}'",
+                        value
+                    )
+                })?);
+            }
+            "--speed" => parsed.speed = Some(number(&arg, &value, SPEED_LIMITS)?),
+            "--apples" => parsed.apples = Some(number(&arg, &value, APPLE_LIMITS)?),
+            "--width" => parsed.width = Some(number(&arg, &value, (MIN_SIZE, MAX_SIZE))?),
+            "--height" => parsed.height = Some(number(&arg, &value, (MIN_SIZE, MAX_SIZE))?),
+            "--level" => parsed.level = Some(PathBuf::from(value)),
+            "--bot" => parsed.bot = Some(value),
+            "--battlesnake" => parsed.battlesnake = Some(value),
+            "--bot-timeout" => parsed.bot_timeout = Some(number(&arg, &value, (1, 5000))?),
+            "--games" => parsed.games = Some(number(&arg, &value, (1, 1000))?),
+            "--swiss" => parsed.swiss = Some(number(&arg, &value, (1, 1000))?),
+            "--threads" => parsed.threads = Some(number(&arg, &value, (1, 64))?),
+            "--seed" => parsed.seed = Some(number(&arg, &value, (0, u64::MAX))?),
+            "--mode" => {
+                let mode = RenderMode::ALL
+                    .into_iter()
+                    .find(|mode| mode.name() == value);
+                parsed.mode = Some(mode.ok_or_else(|| {
+                    format!("--mode should be normal, double or half, got '{}'", value)
+                })?);
+            }
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+    if parsed.help {
+        cli.command = Command::Help;
+        return Ok(cli);
+    }
+    let sound_options = cli.audio_dir.is_some() || cli.sound_pack.is_some();
+    if sound_options && !matches!(parsed.command.as_deref(), None | Some("play")) {
+        return Err(String::from(
+            "sound options can be used only with 'play' or without a command",
+        ));
+    }
+    cli.command = command(parsed)?;
+    Ok(cli)
+}
+
+fn command(parsed: Parsed) -> Result<Command, String> {
+    let size = match (parsed.width, parsed.height) {
+        (None, None) => None,
+        (width, height) => Some(Size::new(
+            width.unwrap_or(Size::default().cols),
+            height.unwrap_or(Size::default().rows),
+        )),
+    };
+    let name = parsed.command.as_deref();
+    let play_options = parsed.speed.is_some()
+        || parsed.mode.is_some()
+        || parsed.no_audio
+        || parsed.bot.is_some()
+        || parsed.battlesnake.is_some();
+    if name != Some("play") && play_options {
+        return Err(String::from("game options can be used only with 'play'"));
+    }
+    let tournament_options =
+        parsed.games.is_some() || parsed.swiss.is_some() || parsed.threads.is_some() || parsed.json;
+    if name != Some("tournament") && tournament_options {
+        return Err(String::from(
+            "tournament options can be used only with 'tournament'",
+        ));
+    }
+    let shared_options = parsed.apples.is_some()
+        || parsed.level.is_some()
+        || parsed.seed.is_some()
+        || parsed.bot_timeout.is_some();
+    if !matches!(name, Some("play") | Some("tournament")) && shared_options {
+        return Err(String::from(
+            "game options can be used only with 'play' and 'tournament'",
+        ));
+    }
+    if !matches!(name, Some("play") | Some("edit") | Some("tournament")) && size.is_some() {
+        return Err(String::from(
+            "--width and --height can be used only with 'play', 'edit' and 'tournament'",
+        ));
+    }
+    if parsed.level.is_some() && size.is_some() {
+        return Err(String::from(
+            "level has its own size, --width and --height can't be used with --level",
+        ));
+    }
+    let targets = match name {
+        Some("replay") | Some("edit") => 1,
+        Some("tournament") => usize::MAX,
+        _ => 0,
+    };
+    if let Some(extra) = parsed.targets.get(targets) {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    let target = || {
+        parsed
+            .targets
+            .first()
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("'{}' needs a file", name.unwrap_or_default()))
+    };
+    Ok(match name {
+        None => Command::Menu,
+        Some("help") => Command::Help,
+        Some("scores") => Command::Scores,
+        Some("stats") => Command::Stats,
+        Some("replay") => Command::Replay(target()?),
+        Some("edit") => Command::Edit(target()?, size.unwrap_or_default()),
+        Some("play") => {
+            if parsed.bot.is_some() && parsed.battlesnake.is_some() {
+                return Err(String::from("only one of --bot and --battlesnake can play"));
+            }
+            Command::Play(PlayOptions {
+                speed: parsed.speed,
+                apples: parsed.apples,
+                size,
+                level: parsed.level,
+                seed: parsed.seed,
+                mode: parsed.mode,
+                no_audio: parsed.no_audio,
+                bot: parsed.bot,
+                battlesnake: parsed.battlesnake,
+                bot_timeout: parsed.bot_timeout,
+            })
+        }
+        Some("tournament") => {
+            if parsed.targets.len() < 2 {
+                return Err(String::from("'tournament' needs at least two players"));
+            }
+            Command::Tournament(TournamentOptions {
+                players: parsed.targets,
+                games: parsed.games.unwrap_or(10),
+                swiss: parsed.swiss,
+                threads: parsed.threads,
+                seed: parsed.seed,
+                apples: parsed.apples,
+                size,
+                level: parsed.level,
+                bot_timeout: parsed.bot_timeout,
+                json: parsed.json,
+            })
+        }
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(line: &str) -> Result<Cli, String> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(parse_str("").unwrap().command, Command::Menu);
+        assert_eq!(parse_str("scores").unwrap().command, Command::Scores);
+        assert_eq!(parse_str("stats --help").unwrap().command, Command::Help);
+        assert_eq!(
+            parse_str("replay game.json").unwrap().command,
+            Command::Replay(PathBuf::from("game.json"))
+        );
+        assert_eq!(
+            parse_str("edit maze.txt --width 40").unwrap().command,
+            Command::Edit(PathBuf::from("maze.txt"), Size::new(40, 30))
+        );
+        let cli = parse_str("--profile bob stats").unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("bob"));
+        let cli = parse_str("--log-level debug").unwrap();
+        assert_eq!(cli.log_level, Some(LevelFilter::Debug));
+        let cli = parse_str("--sound-pack retro").unwrap();
+        assert_eq!(cli.sound_pack, Some(PathBuf::from("retro")));
+    }
+
+    #[test]
+    fn test_play_options() {
+        let cli = parse_str("play --speed 150 --apples 2 --height 20 --seed 7 --mode half --no-audio --audio-dir snd").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Play(PlayOptions {
+                speed: Some(150),
+                apples: Some(2),
+                size: Some(Size::new(30, 20)),
+                level: None,
+                seed: Some(7),
+                mode: Some(RenderMode::HalfBlock),
+                no_audio: true,
+                bot: None,
+                battlesnake: None,
+                bot_timeout: None,
+            })
+        );
+        let args = ["play", "--bot", "python3 bot.py", "--bot-timeout", "20"];
+        match parse(args.map(String::from)).unwrap().command {
+            Command::Play(options) => {
+                assert_eq!(options.bot.as_deref(), Some("python3 bot.py"));
+                assert_eq!(options.bot_timeout, Some(20));
+            }
+            command => panic!("Not play: {:?}", command),
+        }
+        match parse_str("play --battlesnake http://localhost:8000")
+            .unwrap()
+            .command
+        {
+            Command::Play(options) => {
+                assert_eq!(
+                    options.battlesnake.as_deref(),
+                    Some("http://localhost:8000")
+                )
+            }
+            command => panic!("Not play: {:?}", command),
+        }
+        assert_eq!(cli.audio_dir, Some(PathBuf::from("snd")));
+    }
+
+    #[test]
+    fn test_tournament() {
+        let args = [
+            "tournament",
+            "greedy",
+            "python3 bot.py",
+            "--swiss",
+            "3",
+            "--json",
+            "--seed",
+            "5",
+        ];
+        assert_eq!(
+            parse(args.map(String::from)).unwrap().command,
+            Command::Tournament(TournamentOptions {
+                players: vec![String::from("greedy"), String::from("python3 bot.py")],
+                games: 10,
+                swiss: Some(3),
+                threads: None,
+                seed: Some(5),
+                apples: None,
+                size: None,
+                level: None,
+                bot_timeout: None,
+                json: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for line in [
+            "fly",
+            "play --speed 5",
+            "play --width",
+            "play --mode round",
+            "play --level a.txt --width 20",
+            "scores --seed 3",
+            "replay",
+            "stats extra",
+            "play --color red",
+            "play --bot ./bot --battlesnake http://localhost",
+            "tournament greedy",
+            "--log-level chatty",
+            "tournament greedy random --speed 200",
+            "play --games 3",
+            "replay a.json b.json",
+            "scores --audio-dir snd",
+            "stats --sound-pack retro",
+            "edit maze.txt --audio-dir snd",
+            "replay a.json --sound-pack retro",
+            "tournament greedy random --audio-dir snd",
+        ] {
+            assert!(parse_str(line).is_err(), "'{}' should fail", line);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use crate::frame::{draw_overlay, Drawable, Frame};
+
+/// Log lines shown under the numbers.
+const LOG_LINES: usize = 3;
+
+/// Numbers for finding slow frames, toggled in the game with F3. Shown
+/// over the top of the board, so the board doesn't move: frames per
+/// second over the last second, time the last frame took without
+/// waiting, cells the renderer wrote for the last screen and the last
+/// lines of the log.
+#[derive(Default, Debug)]
+pub struct DebugOverlay {
+    visible: bool,
+    fps: usize,
+    frames: usize,
+    second: Duration,
+    tick: Duration,
+    diff_cells: usize,
+    log: Vec<String>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Once a frame, `delta` is the time since the last frame and `tick`
+    /// the time the game spent on it.
+    pub fn frame(&mut self, delta: Duration, tick: Duration) {
+        self.frames += 1;
+        self.second += delta;
+        if self.second >= Duration::from_secs(1) {
+            self.fps = self.frames;
+            self.frames = 0;
+            self.second = Duration::ZERO;
+        }
+        self.tick = tick;
+    }
+
+    pub fn set_diff_cells(&mut self, cells: usize) {
+        self.diff_cells = cells;
+    }
+
+    /// Newest lines of the log, only the last few are shown.
+    pub fn set_log(&mut self, lines: Vec<String>) {
+        self.log = lines;
+    }
+
+    pub fn fps(&self) -> usize {
+        self.fps
+    }
+}
+
+impl Drawable for DebugOverlay {
+    fn draw(&self, frame: &mut Frame) {
+        if !self.visible {
+            return;
+        }
+        draw_overlay(frame, &format!("FPS: {}", self.fps));
+        draw_overlay(
+            frame,
+            &format!("Tick: {:.2}ms", self.tick.as_secs_f64() * 1000.0),
+        );
+        draw_overlay(frame, &format!("Diff: {} cells", self.diff_cells));
+        let skip = self.log.len().saturating_sub(LOG_LINES);
+        for line in &self.log[skip..] {
+            draw_overlay(frame, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::new_frame;
+
+    #[test]
+    fn test_overlay() {
+        let mut overlay = DebugOverlay::new();
+        for _ in 0..30 {
+            overlay.frame(Duration::from_millis(25), Duration::from_micros(1500));
+        }
+        assert_eq!(overlay.fps(), 0, "Less than a second.");
+        for _ in 0..10 {
+            overlay.frame(Duration::from_millis(25), Duration::from_micros(1500));
+        }
+        assert_eq!(overlay.fps(), 40);
+        overlay.set_diff_cells(12);
+        overlay.set_log((0..5).map(|i| format!("INFO line {}", i)).collect());
+
+        let mut frame = new_frame();
+        overlay.draw(&mut frame);
+        assert!(frame.overlay().is_empty(), "Hidden by default.");
+        overlay.toggle();
+        overlay.draw(&mut frame);
+        assert_eq!(
+            frame.overlay(),
+            [
+                "FPS: 40",
+                "Tick: 1.50ms",
+                "Diff: 12 cells",
+                "INFO line 2",
+                "INFO line 3",
+                "INFO line 4"
+            ]
+        );
+    }
+}
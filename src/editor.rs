@@ -0,0 +1,115 @@
+use crate::{
+    frame::{draw_hud, Drawable, Frame},
+    level::Level,
+    snake::{Direction, Snake},
+    Point,
+};
+
+/// Level editor opened with `snake edit <level>`. Cursor is moved over
+/// the board and puts or removes walls, the snake is drawn at its start
+/// so it's easy to see that it is not walled in.
+pub struct Editor {
+    level: Level,
+    cursor: Point,
+    changed: bool,
+}
+
+impl Editor {
+    pub fn new(level: Level) -> Self {
+        Self {
+            cursor: Point::new(0, 0),
+            level,
+            changed: false,
+        }
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    pub fn cursor(&self) -> Point {
+        self.cursor
+    }
+
+    /// Cursor stops at the edges of the board.
+    pub fn move_cursor(&mut self, direction: Direction) {
+        let size = self.level.size;
+        let Point { x, y } = self.cursor;
+        self.cursor = match direction {
+            Direction::Left => Point::new(x.saturating_sub(1), y),
+            Direction::Up => Point::new(x, y.saturating_sub(1)),
+            Direction::Right => Point::new((x + 1).min(size.cols - 1), y),
+            Direction::Down => Point::new(x, (y + 1).min(size.rows - 1)),
+        };
+    }
+
+    /// Walls can't be put where the snake starts, such level can't be
+    /// played.
+    pub fn toggle_wall(&mut self) {
+        if self.level.is_start(self.cursor) {
+            return;
+        }
+        self.level.toggle_wall(self.cursor);
+        self.changed = true;
+    }
+
+    /// Not saved changes.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    pub fn saved(&mut self) {
+        self.changed = false;
+    }
+}
+
+impl Drawable for Editor {
+    fn draw(&self, frame: &mut Frame) {
+        Snake::on_level(self.level.clone(), 0).draw(frame);
+        self.level.draw(frame);
+        frame[self.cursor.x][self.cursor.y] = '+';
+        draw_hud(frame, "Arrows move, Space wall");
+        draw_hud(frame, "S save, Esc quit");
+        let state = if self.changed { "not saved" } else { "saved" };
+        draw_hud(
+            frame,
+            &format!("{},{} {}", self.cursor.x, self.cursor.y, state),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+
+    #[test]
+    fn test_edit() {
+        let mut editor = Editor::new(Level::empty(Size::new(10, 10)));
+        editor.move_cursor(Direction::Left);
+        editor.move_cursor(Direction::Down);
+        editor.move_cursor(Direction::Right);
+        assert_eq!(editor.cursor(), Point::new(1, 1), "Cursor stays on board.");
+        editor.toggle_wall();
+        assert!(editor.level().is_wall(Point::new(1, 1)));
+        assert!(editor.is_changed());
+        editor.saved();
+        assert!(!editor.is_changed());
+        for _ in 0..20 {
+            editor.move_cursor(Direction::Right);
+        }
+        assert_eq!(editor.cursor(), Point::new(9, 1));
+        for _ in 0..5 {
+            editor.move_cursor(Direction::Down);
+        }
+        editor.move_cursor(Direction::Left);
+        editor.move_cursor(Direction::Left);
+        editor.saved();
+        editor.toggle_wall();
+        assert!(
+            !editor.level().is_wall(Point::new(7, 6)),
+            "Snake goes there first."
+        );
+        assert!(!editor.is_changed());
+    }
+}
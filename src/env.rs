@@ -0,0 +1,359 @@
+use crate::{
+    apple::AppleDispencer,
+    bot::GameState,
+    events::{EventQueue, GameEvent},
+    level::Level,
+    snake::{Direction, Snake},
+    topbar::TopBar,
+    Point,
+};
+
+/// Channels of the observation grid, in this order.
+pub const WALLS: usize = 0;
+pub const BODY: usize = 1;
+pub const HEAD: usize = 2;
+pub const APPLES: usize = 3;
+pub const CHANNELS: usize = 4;
+
+/// Length of the feature vector: direction one-hot, danger in every
+/// direction, offset to the nearest apple and length of the snake.
+pub const FEATURES: usize = 11;
+
+/// Reward for every step is a sum of these, set a weight to 0 to turn
+/// a part off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rewards {
+    /// For every eaten apple.
+    pub apple: f32,
+    pub death: f32,
+    /// Every step the snake is alive, negative to hurry it up.
+    pub step: f32,
+    /// Getting closer to the nearest apple, and minus this for moving away.
+    pub closer: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            apple: 1.0,
+            death: -1.0,
+            step: 0.0,
+            closer: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnvConfig {
+    pub level: Level,
+    pub speed: u64,
+    pub apples: u8,
+    pub rewards: Rewards,
+    /// Episode is done after this many steps even if the snake is alive.
+    pub max_steps: Option<usize>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::default(),
+            speed: 100,
+            apples: 3,
+            rewards: Rewards::default(),
+            max_steps: None,
+        }
+    }
+}
+
+/// What the agent sees after reset and every step.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Observation {
+    /// `CHANNELS x rows x cols`, row-major, 1.0 where the channel has
+    /// something and 0.0 elsewhere.
+    pub grid: Vec<f32>,
+    pub rows: usize,
+    pub cols: usize,
+    /// `FEATURES` numbers, see `Environment::features`.
+    pub features: Vec<f32>,
+}
+
+impl Observation {
+    pub fn cell(&self, channel: usize, point: Point) -> f32 {
+        self.grid[(channel * self.rows + point.y) * self.cols + point.x]
+    }
+}
+
+/// Game as a reinforcement learning environment in the style of gym:
+/// `reset` starts an episode, `step` makes one move of the snake and
+/// returns what happened. Time is not real, every step jumps right to the
+/// next move, so episodes run as fast as the simulation does. Nothing
+/// here draws or plays sounds.
+pub struct Environment {
+    config: EnvConfig,
+    snake: Snake,
+    apples: AppleDispencer,
+    topbar: TopBar,
+    events: EventQueue,
+    steps: usize,
+    done: bool,
+}
+
+impl Environment {
+    pub fn new(config: EnvConfig) -> Self {
+        let mut env = Self {
+            snake: Snake::on_level(config.level.clone(), config.speed),
+            apples: AppleDispencer::with_seed(config.apples, 0, config.level.clone()),
+            topbar: TopBar::new(),
+            events: EventQueue::new(),
+            steps: 0,
+            done: false,
+            config,
+        };
+        env.place_apples();
+        env
+    }
+
+    /// New episode, same seed gives the same apples.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        let level = &self.config.level;
+        self.snake = Snake::on_level(level.clone(), self.config.speed);
+        self.apples = AppleDispencer::with_seed(self.config.apples, seed, level.clone());
+        self.topbar = TopBar::new();
+        self.events = EventQueue::new();
+        self.steps = 0;
+        self.done = false;
+        self.place_apples();
+        self.observe()
+    }
+
+    // Apples are placed one a frame, all of them are there at start
+    fn place_apples(&mut self) {
+        for _ in 0..self.config.apples {
+            self.apples.update(Default::default(), &mut self.events);
+        }
+        self.events.dispatch(&mut [&mut self.topbar]);
+    }
+
+    /// Turns the snake if it can turn that way and makes one move.
+    /// Steps after the episode is done change nothing.
+    pub fn step(&mut self, action: Direction) -> (Observation, f32, bool) {
+        if self.done {
+            return (self.observe(), 0.0, true);
+        }
+        let rewards = self.config.rewards;
+        let distance_before = self.apple_distance();
+        self.snake.queue_turn(action);
+        let delta = self.snake.time_to_move();
+        self.snake.update(delta, &mut self.events);
+        self.topbar.update(delta);
+        self.apples.update(delta, &mut self.events);
+        self.snake
+            .check_if_ate_apple(&mut self.apples, &mut self.events);
+        self.steps += 1;
+
+        let mut reward = rewards.step;
+        let mut ate = false;
+        for event in self.events.events() {
+            match event {
+                GameEvent::AteApple { .. } => {
+                    ate = true;
+                    reward += rewards.apple;
+                }
+                GameEvent::Died { .. } => reward += rewards.death,
+                _ => {}
+            }
+        }
+        // Distance jumps when an apple is eaten or gone, that's no progress
+        if let (false, Some(before), Some(after)) = (ate, distance_before, self.apple_distance()) {
+            if after < before {
+                reward += rewards.closer;
+            } else if after > before {
+                reward -= rewards.closer;
+            }
+        }
+        self.events.dispatch(&mut [&mut self.topbar]);
+        self.done =
+            self.snake.is_dead() || self.config.max_steps.is_some_and(|max| self.steps >= max);
+        (self.observe(), reward, self.done)
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn apples(&self) -> &AppleDispencer {
+        &self.apples
+    }
+
+    pub fn topbar(&self) -> &TopBar {
+        &self.topbar
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// State as bots see it.
+    pub fn state(&self) -> GameState {
+        GameState::new(self.steps as u64, &self.snake, &self.apples, &self.topbar)
+    }
+
+    fn apple_distance(&self) -> Option<usize> {
+        let head = self.snake.head();
+        self.apples
+            .nearest(head)
+            .map(|(apple, _)| apple.x.abs_diff(head.x) + apple.y.abs_diff(head.y))
+    }
+
+    /// Cell next to the head kills the snake.
+    fn is_danger(&self, direction: Direction) -> bool {
+        let head = self.snake.head();
+        let size = self.config.level.size;
+        let next = match direction {
+            Direction::Left if head.x > 0 => Point::new(head.x - 1, head.y),
+            Direction::Up if head.y > 0 => Point::new(head.x, head.y - 1),
+            Direction::Right if head.x + 1 < size.cols => Point::new(head.x + 1, head.y),
+            Direction::Down if head.y + 1 < size.rows => Point::new(head.x, head.y + 1),
+            _ => return true,
+        };
+        self.config.level.is_wall(next) || self.snake.body().contains(&next)
+    }
+
+    /// Direction one-hot in `Direction::ALL` order, 1.0 for every direction
+    /// where the next cell is deadly, offset to the nearest apple divided
+    /// by board size and snake length divided by number of cells.
+    pub fn features(&self) -> Vec<f32> {
+        let size = self.config.level.size;
+        let head = self.snake.head();
+        let mut features = Vec::with_capacity(FEATURES);
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        for direction in Direction::ALL {
+            features.push(flag(self.snake.direction() == direction));
+        }
+        for direction in Direction::ALL {
+            features.push(flag(self.is_danger(direction)));
+        }
+        let (dx, dy) = match self.apples.nearest(head) {
+            Some((apple, _)) => (
+                (apple.x as f32 - head.x as f32) / size.cols as f32,
+                (apple.y as f32 - head.y as f32) / size.rows as f32,
+            ),
+            None => (0.0, 0.0),
+        };
+        features.push(dx);
+        features.push(dy);
+        features.push(self.snake.length() as f32 / (size.cols * size.rows) as f32);
+        features
+    }
+
+    pub fn observe(&self) -> Observation {
+        let size = self.config.level.size;
+        let mut observation = Observation {
+            grid: vec![0.0; CHANNELS * size.rows * size.cols],
+            rows: size.rows,
+            cols: size.cols,
+            features: self.features(),
+        };
+        let mut set = |channel: usize, point: Point| {
+            observation.grid[(channel * size.rows + point.y) * size.cols + point.x] = 1.0;
+        };
+        for wall in &self.config.level.walls {
+            set(WALLS, *wall);
+        }
+        for part in self.snake.body() {
+            set(BODY, *part);
+        }
+        set(HEAD, self.snake.head());
+        for (apple, _, _) in self.apples.apples() {
+            set(APPLES, apple);
+        }
+        observation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+
+    #[test]
+    fn test_observation() {
+        let mut level = Level::empty(Size::new(10, 12));
+        level.toggle_wall(Point::new(0, 0));
+        let mut env = Environment::new(EnvConfig {
+            level,
+            apples: 2,
+            ..Default::default()
+        });
+        let started = env.observe();
+        assert_eq!(env.reset(0), started, "New environment is seed 0.");
+        let observation = env.reset(5);
+        assert_eq!(observation.grid.len(), CHANNELS * 12 * 10);
+        assert_eq!(observation.features.len(), FEATURES);
+        assert_eq!(observation.cell(WALLS, Point::new(0, 0)), 1.0);
+        let head = env.snake().head();
+        assert_eq!(observation.cell(HEAD, head), 1.0);
+        assert_eq!(observation.cell(BODY, head), 1.0);
+        let apples: f32 = observation.grid[APPLES * 120..].iter().sum();
+        assert_eq!(apples, 2.0);
+        // Going right at the start, the tail is behind
+        assert_eq!(&observation.features[..8], [0., 0., 1., 0., 1., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut env = Environment::new(EnvConfig {
+            rewards: Rewards {
+                step: -0.01,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        env.reset(1);
+        let head = env.snake().head();
+        let (observation, reward, done) = env.step(Direction::Up);
+        assert_eq!(env.snake().head(), Point::new(head.x, head.y - 1));
+        assert_eq!(observation.cell(HEAD, env.snake().head()), 1.0);
+        assert!(!done);
+        assert!(reward == -0.01 || reward > 0.9, "Step or apple.");
+
+        let mut last = (observation, reward, done);
+        while !last.2 {
+            last = env.step(Direction::Up);
+        }
+        assert!(env.snake().is_dead());
+        assert!((last.1 + 1.01).abs() < 1e-6, "Died in a step.");
+        assert_eq!(env.step(Direction::Left).1, 0.0, "Nothing after done.");
+    }
+
+    #[test]
+    fn test_same_seed_same_episode() {
+        let play = |env: &mut Environment| {
+            let mut observation = env.reset(11);
+            let mut total = 0.0;
+            for step in 0..200 {
+                let (next, reward, done) = env.step(Direction::ALL[step / 5 % 4]);
+                observation = next;
+                total += reward;
+                if done {
+                    break;
+                }
+            }
+            (observation, total, env.steps())
+        };
+        let config = EnvConfig {
+            rewards: Rewards {
+                closer: 0.1,
+                ..Default::default()
+            },
+            max_steps: Some(150),
+            ..Default::default()
+        };
+        let mut env = Environment::new(config);
+        assert_eq!(play(&mut env), play(&mut env));
+    }
+}
@@ -0,0 +1,104 @@
+//! Simulation doesn't know who is interested in what happens in the game.
+//! `Snake` and `AppleDispencer` only push events into the queue and once
+//! a frame main loop hands them to every listener: audio, scores and so on.
+//! New listener is one more item in the dispatch list.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{apple::AppleKind, snake::Direction, Point};
+
+/// What the snake ran into.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DeathCause {
+    Wall,
+    Tail,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    Moved { head: Point },
+    Turned { direction: Direction },
+    AteApple { kind: AppleKind, pos: Point },
+    AppleSpawned { kind: AppleKind, pos: Point },
+    AppleExpired { pos: Point },
+    Died { cause: DeathCause },
+}
+
+pub trait Listener {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+#[derive(Default, Debug)]
+pub struct EventQueue {
+    events: Vec<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Gives every queued event to each listener in order and empties queue.
+    pub fn dispatch(&mut self, listeners: &mut [&mut dyn Listener]) {
+        for event in self.events.drain(..) {
+            for listener in listeners.iter_mut() {
+                listener.on_event(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        moves: usize,
+        deaths: usize,
+    }
+
+    impl Listener for Counter {
+        fn on_event(&mut self, event: &GameEvent) {
+            match event {
+                GameEvent::Moved { .. } => self.moves += 1,
+                GameEvent::Died { .. } => self.deaths += 1,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let mut queue = EventQueue::new();
+        queue.push(GameEvent::Moved {
+            head: Point::new(1, 1),
+        });
+        queue.push(GameEvent::Moved {
+            head: Point::new(2, 1),
+        });
+        queue.push(GameEvent::Died {
+            cause: DeathCause::Wall,
+        });
+        let mut first = Counter::default();
+        let mut second = Counter::default();
+        queue.dispatch(&mut [&mut first, &mut second]);
+        assert!(queue.is_empty(), "Queue is empty after dispatch.");
+        for counter in [first, second] {
+            assert_eq!(counter.moves, 2, "Each listener gets all events.");
+            assert_eq!(counter.deaths, 1);
+        }
+    }
+}
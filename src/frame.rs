@@ -1,10 +1,78 @@
-use crate::Point;
-use crate::{NUM_COLS, NUM_ROWS};
+use std::ops::{Index, IndexMut};
 
-pub type Frame = [[char; NUM_ROWS]; NUM_COLS];
+use crate::{Point, Size};
+
+/// Board cells plus text drawn over them. Text is kept apart, because
+/// in double width mode cells are stretched, but letters should not be.
+/// HUD lines are shown above the board, outside of it. Overlay lines are
+/// put over the top of the board after it is composed, so they don't move
+/// the layout.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    cells: Vec<Vec<char>>,
+    texts: Vec<(Point, String)>,
+    hud: Vec<String>,
+    overlay: Vec<String>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::with_size(Size::default())
+    }
+
+    /// Frame for a board of another size, menus always use the default one.
+    pub fn with_size(size: Size) -> Self {
+        Self {
+            cells: vec![vec![' '; size.rows]; size.cols],
+            texts: Vec::new(),
+            hud: Vec::new(),
+            overlay: Vec::new(),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    pub fn texts(&self) -> &[(Point, String)] {
+        &self.texts
+    }
+
+    pub fn hud(&self) -> &[String] {
+        &self.hud
+    }
+
+    pub fn overlay(&self) -> &[String] {
+        &self.overlay
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<usize> for Frame {
+    type Output = [char];
+
+    fn index(&self, x: usize) -> &Self::Output {
+        &self.cells[x]
+    }
+}
+
+impl IndexMut<usize> for Frame {
+    fn index_mut(&mut self, x: usize) -> &mut Self::Output {
+        &mut self.cells[x]
+    }
+}
 
 pub fn new_frame() -> Frame {
-    [[' '; NUM_ROWS]; NUM_COLS]
+    Frame::new()
 }
 
 pub trait Drawable {
@@ -16,7 +84,15 @@ pub trait Reset {
 }
 
 pub fn draw_text(frame: &mut Frame, start_at: Point, text: &str) {
-    for (index, char) in text.chars().enumerate() {
-        frame[start_at.x + index][start_at.y] = char;
-    }
+    frame.texts.push((start_at, String::from(text)));
+}
+
+/// Adds a line above the board.
+pub fn draw_hud(frame: &mut Frame, text: &str) {
+    frame.hud.push(String::from(text));
+}
+
+/// Adds a line over the top left of the board.
+pub fn draw_overlay(frame: &mut Frame, text: &str) {
+    frame.overlay.push(String::from(text));
 }
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apple::AppleDispencer,
+    frame::{draw_hud, Drawable, Frame},
+    snake::Snake,
+    topbar::TopBar,
+};
+
+/// Pieces of live information above the board. Which ones are shown is
+/// chosen in settings, they are always drawn in the order of `ALL` and
+/// wrapped to the board width by the layout.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HudWidget {
+    Score,
+    Time,
+    Length,
+    Speed,
+    HighScore,
+    AppleExpiry,
+    Seed,
+}
+
+impl HudWidget {
+    pub const ALL: [HudWidget; 7] = [
+        HudWidget::Score,
+        HudWidget::Time,
+        HudWidget::Length,
+        HudWidget::Speed,
+        HudWidget::HighScore,
+        HudWidget::AppleExpiry,
+        HudWidget::Seed,
+    ];
+
+    pub const DEFAULT: [HudWidget; 4] = [
+        HudWidget::Score,
+        HudWidget::Time,
+        HudWidget::Length,
+        HudWidget::HighScore,
+    ];
+
+    /// Stable id, also used for the menu item.
+    pub fn id(&self) -> &'static str {
+        match self {
+            HudWidget::Score => "hud_score",
+            HudWidget::Time => "hud_time",
+            HudWidget::Length => "hud_length",
+            HudWidget::Speed => "hud_speed",
+            HudWidget::HighScore => "hud_high_score",
+            HudWidget::AppleExpiry => "hud_apple_expiry",
+            HudWidget::Seed => "hud_seed",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HudWidget::Score => "Score",
+            HudWidget::Time => "Time",
+            HudWidget::Length => "Length",
+            HudWidget::Speed => "Speed",
+            HudWidget::HighScore => "Best",
+            HudWidget::AppleExpiry => "Apple",
+            HudWidget::Seed => "Seed",
+        }
+    }
+}
+
+/// Everything the HUD reads from the running game.
+pub struct Hud<'a> {
+    pub widgets: &'a [HudWidget],
+    pub topbar: &'a TopBar,
+    pub snake: &'a Snake,
+    pub apples: &'a AppleDispencer,
+    /// Best score before this game.
+    pub high_score: usize,
+}
+
+impl Hud<'_> {
+    fn value(&self, widget: HudWidget) -> String {
+        match widget {
+            HudWidget::Score => format!("{:04}", self.topbar.get_scores()),
+            HudWidget::Time => format!("{:05}", self.topbar.get_time()),
+            HudWidget::Length => self.snake.length().to_string(),
+            HudWidget::Speed => format!("{:.1}/s", 1000.0 / self.snake.speed().max(1) as f64),
+            HudWidget::HighScore => self.high_score.max(self.topbar.get_scores()).to_string(),
+            // Apple closest to the head, that's the one player goes for
+            HudWidget::AppleExpiry => match self.apples.nearest(self.snake.head()) {
+                Some((_, left)) => format!("{}s", left.as_secs_f64().ceil()),
+                None => String::from("-"),
+            },
+            HudWidget::Seed => self.apples.seed().to_string(),
+        }
+    }
+}
+
+impl Drawable for Hud<'_> {
+    fn draw(&self, frame: &mut Frame) {
+        for widget in HudWidget::ALL {
+            if self.widgets.contains(&widget) {
+                let item = format!("{}: {}", widget.label(), self.value(widget));
+                draw_hud(frame, &item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{frame::new_frame, level::Level};
+
+    #[test]
+    fn test_widgets() {
+        let mut topbar = TopBar::new();
+        for _ in 0..7 {
+            topbar.scores();
+        }
+        topbar.update(Duration::from_secs(75));
+        let snake = Snake::new(250);
+        let apples = AppleDispencer::with_seed(3, 42, Level::default());
+        let widgets = [HudWidget::Seed, HudWidget::Score, HudWidget::Speed];
+        let hud = Hud {
+            widgets: &widgets,
+            topbar: &topbar,
+            snake: &snake,
+            apples: &apples,
+            high_score: 5,
+        };
+        let mut frame = new_frame();
+        hud.draw(&mut frame);
+        assert_eq!(
+            frame.hud(),
+            ["Score: 0007", "Speed: 4.0/s", "Seed: 42"],
+            "Widgets are drawn in fixed order."
+        );
+        assert_eq!(
+            hud.value(HudWidget::HighScore),
+            "7",
+            "Current score is best now."
+        );
+        assert_eq!(hud.value(HudWidget::AppleExpiry), "-", "No apples yet.");
+    }
+}
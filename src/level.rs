@@ -0,0 +1,178 @@
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    frame::{Drawable, Frame},
+    Point, Size,
+};
+
+pub const MIN_SIZE: usize = 10;
+pub const MAX_SIZE: usize = 100;
+const WALL: char = '#';
+const EMPTY: char = '.';
+/// Snake starts with this many cells.
+const START_LENGTH: usize = 4;
+
+/// Board of the game: its size and walls inside of it. Level file is
+/// plain text, one line per row, `#` is a wall and `.` is an empty cell,
+/// so it can be drawn by hand as well as in the editor.
+#[derive(Clone, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct Level {
+    pub size: Size,
+    pub walls: BTreeSet<Point>,
+}
+
+impl Level {
+    pub fn empty(size: Size) -> Self {
+        Self {
+            size,
+            walls: BTreeSet::new(),
+        }
+    }
+
+    /// Head of the snake at the start, its body goes to the left.
+    pub fn start(&self) -> Point {
+        Point::new(self.size.cols * 2 / 3, self.size.rows * 2 / 3)
+    }
+
+    /// Cells of the snake at the start, head first.
+    pub fn start_body(&self) -> Vec<Point> {
+        let head = self.start();
+        (0..START_LENGTH)
+            .map(|i| Point::new(head.x - i, head.y))
+            .collect()
+    }
+
+    /// Snake's cells and the one it goes into first, a wall there would
+    /// put the snake into it or kill it on the first move.
+    pub fn is_start(&self, point: Point) -> bool {
+        let head = self.start();
+        point.y == head.y && point.x + START_LENGTH > head.x && point.x <= head.x + 1
+    }
+
+    fn check_start(&self) -> io::Result<()> {
+        match self.walls.iter().find(|wall| self.is_start(**wall)) {
+            Some(wall) => Err(invalid(format!(
+                "wall at {},{} is where the snake starts",
+                wall.x, wall.y
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_wall(&self, point: Point) -> bool {
+        self.walls.contains(&point)
+    }
+
+    pub fn toggle_wall(&mut self, point: Point) {
+        if !self.walls.remove(&point) && self.size.contains(point) {
+            self.walls.insert(point);
+        }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let lines: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+        let size = Size::new(
+            lines.first().map_or(0, |line| line.chars().count()),
+            lines.len(),
+        );
+        if !(MIN_SIZE..=MAX_SIZE).contains(&size.cols)
+            || !(MIN_SIZE..=MAX_SIZE).contains(&size.rows)
+        {
+            return Err(invalid(format!(
+                "level is {}x{}, it should be from {} to {} cells each side",
+                size.cols, size.rows, MIN_SIZE, MAX_SIZE
+            )));
+        }
+        let mut level = Self::empty(size);
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() != size.cols {
+                return Err(invalid(format!("line {} has different length", y + 1)));
+            }
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    WALL => {
+                        level.walls.insert(Point::new(x, y));
+                    }
+                    EMPTY => {}
+                    _ => return Err(invalid(format!("unknown cell '{}' on line {}", c, y + 1))),
+                }
+            }
+        }
+        level.check_start()?;
+        Ok(level)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.size.rows {
+            for x in 0..self.size.cols {
+                text.push(if self.is_wall(Point::new(x, y)) {
+                    WALL
+                } else {
+                    EMPTY
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.check_start()?;
+        fs::write(path, self.to_text())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Drawable for Level {
+    fn draw(&self, frame: &mut Frame) {
+        for wall in &self.walls {
+            frame[wall.x][wall.y] = WALL;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut level = Level::empty(Size::new(12, 10));
+        level.toggle_wall(Point::new(0, 0));
+        level.toggle_wall(Point::new(11, 9));
+        level.toggle_wall(Point::new(5, 5));
+        level.toggle_wall(Point::new(5, 5));
+        level.toggle_wall(Point::new(12, 0));
+        assert_eq!(level.walls.len(), 2, "Toggled twice or outside is no wall.");
+        let text = level.to_text();
+        assert!(text.starts_with("#...........\n"));
+        assert_eq!(Level::parse(&text).unwrap(), level);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Level::parse("..\n..\n").is_err(), "Too small.");
+        let mut text = Level::empty(Size::new(10, 10)).to_text();
+        text.replace_range(0..1, "x");
+        assert!(Level::parse(&text).is_err(), "Unknown cell.");
+        text.replace_range(0..1, "");
+        assert!(Level::parse(&text).is_err(), "Short line.");
+
+        // Snake starts at 3..=6,6 going right
+        for (x, ok) in [(2, true), (3, false), (6, false), (7, false), (8, true)] {
+            let mut level = Level::empty(Size::new(10, 10));
+            level.toggle_wall(Point::new(x, 6));
+            assert_eq!(Level::parse(&level.to_text()).is_ok(), ok, "Wall at {}.", x);
+        }
+    }
+}
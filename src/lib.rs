@@ -1,16 +1,41 @@
+pub mod ai;
 pub mod apple;
 pub mod audio;
+pub mod audio_backend;
+pub mod battlesnake;
+pub mod bot;
+pub mod cli;
+pub mod debug;
+pub mod editor;
+pub mod env;
+pub mod events;
 pub mod frame;
+pub mod hud;
+pub mod level;
+pub mod list_menu;
+pub mod logger;
 pub mod menu;
+pub mod mixer;
+pub mod music;
 pub mod render;
+pub mod replay;
+pub mod savegame;
+pub mod screen;
+pub mod settings;
 pub mod snake;
+pub mod sound_pack;
+pub mod stats;
+pub mod storage;
 pub mod timer;
 pub mod topbar;
+pub mod tournament;
+
+use serde::{Deserialize, Serialize};
 
 pub const NUM_ROWS: usize = 30;
 pub const NUM_COLS: usize = 30;
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct Point {
     pub x: usize,
     pub y: usize,
@@ -21,3 +46,26 @@ impl Point {
         Self { x, y }
     }
 }
+
+/// Board size in cells.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Size {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Size {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self { cols, rows }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x < self.cols && point.y < self.rows
+    }
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        Self::new(NUM_COLS, NUM_ROWS)
+    }
+}
@@ -0,0 +1,518 @@
+//! Menu built from a list of items instead of hardcoded options. Items are
+//! found by their ids, so owner of the menu reads values and reacts on
+//! actions without knowing where the item is shown. Submenu is just an
+//! item with its own list, `back` returns from it. Layout is computed from
+//! the frame size, long lists scroll to keep selected item visible.
+
+use crate::{
+    frame::{draw_text, Drawable, Frame},
+    Point,
+};
+
+const MARGIN_TOP: usize = 3;
+const MARGIN_BOTTOM: usize = 5;
+const MAX_WIDTH: usize = 26;
+const VALUE_WIDTH: usize = 10;
+
+pub enum ItemValue {
+    Range {
+        value: i64,
+        min: i64,
+        max: i64,
+        step: i64,
+    },
+    Choice {
+        options: Vec<String>,
+        selected: usize,
+    },
+    Toggle(bool),
+    Action,
+    Submenu(Vec<MenuItem>),
+}
+
+pub struct MenuItem {
+    pub id: &'static str,
+    pub label: String,
+    pub value: ItemValue,
+    pub hidden: bool,
+}
+
+impl MenuItem {
+    fn new(id: &'static str, label: &str, value: ItemValue) -> Self {
+        Self {
+            id,
+            label: String::from(label),
+            value,
+            hidden: false,
+        }
+    }
+
+    pub fn range(id: &'static str, label: &str, value: i64, min: i64, max: i64, step: i64) -> Self {
+        Self::new(
+            id,
+            label,
+            ItemValue::Range {
+                value: value.clamp(min, max),
+                min,
+                max,
+                step,
+            },
+        )
+    }
+
+    pub fn choice(id: &'static str, label: &str, options: Vec<String>, selected: usize) -> Self {
+        Self::new(id, label, ItemValue::Choice { options, selected })
+    }
+
+    pub fn toggle(id: &'static str, label: &str, on: bool) -> Self {
+        Self::new(id, label, ItemValue::Toggle(on))
+    }
+
+    pub fn action(id: &'static str, label: &str) -> Self {
+        Self::new(id, label, ItemValue::Action)
+    }
+
+    pub fn submenu(id: &'static str, label: &str, items: Vec<MenuItem>) -> Self {
+        Self::new(id, label, ItemValue::Submenu(items))
+    }
+
+    // Increase or decrease value by one step, ranges stop at limits
+    // and choices go around.
+    fn change(&mut self, forward: bool) {
+        match &mut self.value {
+            ItemValue::Range {
+                value,
+                min,
+                max,
+                step,
+            } => {
+                let next = if forward {
+                    *value + *step
+                } else {
+                    *value - *step
+                };
+                *value = next.clamp(*min, *max);
+            }
+            ItemValue::Choice { options, selected } if !options.is_empty() => {
+                let len = options.len();
+                *selected = if forward {
+                    (*selected + 1) % len
+                } else {
+                    (*selected + len - 1) % len
+                };
+            }
+            ItemValue::Toggle(on) => *on = !*on,
+            _ => {}
+        }
+    }
+
+    fn display_value(&self) -> String {
+        match &self.value {
+            ItemValue::Range { value, .. } => value.to_string(),
+            ItemValue::Choice { options, selected } => options
+                .get(*selected)
+                .cloned()
+                .unwrap_or_else(|| String::from("none")),
+            ItemValue::Toggle(on) => String::from(if *on { "on" } else { "off" }),
+            ItemValue::Action => String::new(),
+            ItemValue::Submenu(_) => String::from("..."),
+        }
+    }
+
+    fn is_adjustable(&self) -> bool {
+        matches!(
+            self.value,
+            ItemValue::Range { .. } | ItemValue::Choice { .. } | ItemValue::Toggle(_)
+        )
+    }
+}
+
+fn find<'a>(items: &'a [MenuItem], id: &str) -> Option<&'a MenuItem> {
+    items.iter().find_map(|item| match &item.value {
+        _ if item.id == id => Some(item),
+        ItemValue::Submenu(sub) => find(sub, id),
+        _ => None,
+    })
+}
+
+fn find_mut<'a>(items: &'a mut [MenuItem], id: &str) -> Option<&'a mut MenuItem> {
+    for item in items.iter_mut() {
+        if item.id == id {
+            return Some(item);
+        }
+        if let ItemValue::Submenu(sub) = &mut item.value {
+            if let Some(found) = find_mut(sub, id) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+pub struct ListMenu {
+    items: Vec<MenuItem>,
+    /// Selected index on each opened level, the last one is current.
+    path: Vec<usize>,
+}
+
+impl ListMenu {
+    pub fn new(items: Vec<MenuItem>) -> Self {
+        let mut menu = Self {
+            items,
+            path: vec![0],
+        };
+        menu.skip_hidden(true);
+        menu
+    }
+
+    fn level(&self) -> &[MenuItem] {
+        let mut items = &self.items;
+        for &index in &self.path[..self.path.len() - 1] {
+            items = match &items[index].value {
+                ItemValue::Submenu(sub) => sub,
+                _ => unreachable!("Only submenus are opened."),
+            };
+        }
+        items
+    }
+
+    fn level_mut(&mut self) -> &mut [MenuItem] {
+        let mut items = &mut self.items;
+        for &index in &self.path[..self.path.len() - 1] {
+            items = match &mut items[index].value {
+                ItemValue::Submenu(sub) => sub,
+                _ => unreachable!("Only submenus are opened."),
+            };
+        }
+        items
+    }
+
+    fn selected_index(&self) -> usize {
+        *self.path.last().expect("Root level is always open.")
+    }
+
+    fn selected_item(&self) -> Option<&MenuItem> {
+        self.level().get(self.selected_index())
+    }
+
+    pub fn selected_id(&self) -> Option<&'static str> {
+        self.selected_item().map(|item| item.id)
+    }
+
+    /// Submenu which is open now, `None` on the root level.
+    pub fn open_submenu(&self) -> Option<&str> {
+        if self.path.len() < 2 {
+            return None;
+        }
+        let mut items = &self.items;
+        let mut label = None;
+        for &index in &self.path[..self.path.len() - 1] {
+            label = Some(items[index].label.as_str());
+            if let ItemValue::Submenu(sub) = &items[index].value {
+                items = sub;
+            }
+        }
+        label
+    }
+
+    // Moves selection off hidden item in given direction.
+    fn skip_hidden(&mut self, forward: bool) {
+        let len = self.level().len();
+        for _ in 0..len {
+            let index = self.selected_index();
+            if !self.level()[index].hidden {
+                return;
+            }
+            self.step(forward);
+        }
+    }
+
+    fn step(&mut self, forward: bool) {
+        let len = self.level().len();
+        if len == 0 {
+            return;
+        }
+        let index = self.path.last_mut().unwrap();
+        *index = if forward {
+            (*index + 1) % len
+        } else {
+            (*index + len - 1) % len
+        };
+    }
+
+    pub fn down(&mut self) {
+        self.step(true);
+        self.skip_hidden(true);
+    }
+
+    pub fn up(&mut self) {
+        self.step(false);
+        self.skip_hidden(false);
+    }
+
+    pub fn right(&mut self) {
+        let index = self.selected_index();
+        if let Some(item) = self.level_mut().get_mut(index) {
+            item.change(true);
+        }
+    }
+
+    pub fn left(&mut self) {
+        let index = self.selected_index();
+        if let Some(item) = self.level_mut().get_mut(index) {
+            item.change(false);
+        }
+    }
+
+    /// Returns id of chosen action, submenu is opened and toggle is flipped.
+    pub fn enter(&mut self) -> Option<&'static str> {
+        let index = self.selected_index();
+        let item = self.level_mut().get_mut(index)?;
+        match &item.value {
+            ItemValue::Action => return Some(item.id),
+            ItemValue::Toggle(_) => item.change(true),
+            ItemValue::Submenu(sub) if !sub.is_empty() => {
+                self.path.push(0);
+                self.skip_hidden(true);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Closes open submenu, returns false if already on the root level.
+    pub fn back(&mut self) -> bool {
+        if self.path.len() < 2 {
+            return false;
+        }
+        self.path.pop();
+        true
+    }
+
+    /// Selects item on the current level.
+    pub fn select(&mut self, id: &str) {
+        if let Some(index) = self
+            .level()
+            .iter()
+            .position(|item| item.id == id && !item.hidden)
+        {
+            *self.path.last_mut().unwrap() = index;
+        }
+    }
+
+    pub fn set_hidden(&mut self, id: &str, hidden: bool) {
+        if let Some(item) = find_mut(&mut self.items, id) {
+            item.hidden = hidden;
+        }
+        self.skip_hidden(true);
+    }
+
+    pub fn set_choices(&mut self, id: &str, options: Vec<String>, selected: usize) {
+        if let Some(item) = find_mut(&mut self.items, id) {
+            item.value = ItemValue::Choice { options, selected };
+        }
+    }
+
+    pub fn range_value(&self, id: &str) -> Option<i64> {
+        match find(&self.items, id)?.value {
+            ItemValue::Range { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_value(&self, id: &str) -> Option<bool> {
+        match find(&self.items, id)?.value {
+            ItemValue::Toggle(on) => Some(on),
+            _ => None,
+        }
+    }
+
+    pub fn choice_value(&self, id: &str) -> Option<&str> {
+        match &find(&self.items, id)?.value {
+            ItemValue::Choice { options, selected } => options.get(*selected).map(|o| o.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl Drawable for ListMenu {
+    fn draw(&self, frame: &mut Frame) {
+        let cols = frame.cols();
+        let rows = frame.rows();
+        let width = MAX_WIDTH.min(cols.saturating_sub(2));
+        let left = (cols - width) / 2;
+        let mut top = MARGIN_TOP;
+        if let Some(title) = self.open_submenu() {
+            let title: String = title.chars().take(width).collect();
+            let x = cols.saturating_sub(title.chars().count()) / 2;
+            draw_text(frame, Point::new(x, top), &title);
+            top += 2;
+        }
+        let capacity = rows.saturating_sub(top + MARGIN_BOTTOM).max(1);
+
+        let visible: Vec<(usize, &MenuItem)> = self
+            .level()
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| !item.hidden)
+            .collect();
+        let selected = visible
+            .iter()
+            .position(|(index, _)| *index == self.selected_index())
+            .unwrap_or(0);
+        let first = (selected + 1).saturating_sub(capacity);
+        let label_width = width.saturating_sub(VALUE_WIDTH + 2);
+
+        for (row, (index, item)) in visible.iter().skip(first).take(capacity).enumerate() {
+            let is_selected = *index == self.selected_index();
+            let value = item.display_value();
+            let value = if is_selected && item.is_adjustable() {
+                format!("< {} >", value)
+            } else {
+                value
+            };
+            let line = format!(
+                "{} {:<label_width$.label_width$}{:>VALUE_WIDTH$.VALUE_WIDTH$}",
+                if is_selected { '>' } else { ' ' },
+                item.label,
+                value,
+            );
+            draw_text(frame, Point::new(left, top + row), &line);
+        }
+        let middle = left + width / 2;
+        if first > 0 {
+            draw_text(frame, Point::new(middle, top - 1), "▲");
+        }
+        if first + capacity < visible.len() {
+            draw_text(frame, Point::new(middle, top + capacity), "▼");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::new_frame,
+        screen::{RenderMode, Screen},
+        Size,
+    };
+
+    fn menu() -> ListMenu {
+        ListMenu::new(vec![
+            MenuItem::action("hidden", "Hidden"),
+            MenuItem::action("start", "Start"),
+            MenuItem::range("speed", "Speed", 300, 100, 400, 100),
+            MenuItem::submenu(
+                "sound",
+                "Sound",
+                vec![
+                    MenuItem::choice(
+                        "music",
+                        "Music",
+                        vec![String::from("game"), String::from("menu")],
+                        0,
+                    ),
+                    MenuItem::toggle("mute", "Mute", false),
+                ],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_wrap_around_and_hidden() {
+        let mut menu = menu();
+        menu.set_hidden("hidden", true);
+        assert_eq!(menu.selected_id(), Some("start"));
+        menu.up();
+        assert_eq!(menu.selected_id(), Some("sound"), "Goes around to the end.");
+        menu.down();
+        assert_eq!(menu.selected_id(), Some("start"), "Hidden one is skipped.");
+        menu.set_hidden("hidden", false);
+        menu.up();
+        assert_eq!(menu.selected_id(), Some("hidden"));
+    }
+
+    #[test]
+    fn test_values() {
+        let mut menu = menu();
+        menu.select("speed");
+        menu.right();
+        menu.right();
+        assert_eq!(menu.range_value("speed"), Some(400), "Stops at max.");
+        menu.left();
+        assert_eq!(menu.range_value("speed"), Some(300));
+        assert_eq!(menu.enter(), None);
+
+        menu.select("start");
+        assert_eq!(menu.enter(), Some("start"));
+    }
+
+    #[test]
+    fn test_submenu() {
+        let mut menu = menu();
+        menu.select("sound");
+        assert_eq!(menu.enter(), None);
+        assert_eq!(menu.open_submenu(), Some("Sound"));
+        assert_eq!(menu.selected_id(), Some("music"));
+        menu.left();
+        assert_eq!(
+            menu.choice_value("music"),
+            Some("menu"),
+            "Choices go around."
+        );
+        menu.down();
+        menu.enter();
+        assert_eq!(menu.toggle_value("mute"), Some(true));
+        assert!(menu.back());
+        assert_eq!(menu.selected_id(), Some("sound"));
+        assert!(!menu.back(), "Nothing to close on the root level.");
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let ids = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k"];
+        let items = (0..40)
+            .map(|i| MenuItem::action(ids[i % ids.len()], &format!("Item {}", i)))
+            .collect();
+        let mut menu = ListMenu::new(items);
+        for _ in 0..39 {
+            menu.down();
+        }
+        let mut frame = new_frame();
+        menu.draw(&mut frame);
+        let screen = Screen::compose(&frame, RenderMode::Normal);
+        let text: String = (0..screen.rows()).map(|y| screen.line(y)).collect();
+        assert!(
+            screen
+                .line(screen.rows() - MARGIN_BOTTOM - 1)
+                .contains("> Item 39"),
+            "Selected item is visible."
+        );
+        assert!(text.contains('▲'));
+        assert!(!text.contains('▼'));
+    }
+
+    #[test]
+    fn test_narrow_frame() {
+        let mut menu = ListMenu::new(vec![MenuItem::submenu(
+            "sound",
+            "Звук и музыка",
+            vec![MenuItem::toggle("mute", "Mute", false)],
+        )]);
+        menu.enter();
+        for cols in [1, 8, 14] {
+            let mut frame = Frame::with_size(Size::new(cols, 12));
+            menu.draw(&mut frame);
+            Screen::compose(&frame, RenderMode::Normal);
+        }
+        let mut frame = Frame::with_size(Size::new(30, 12));
+        menu.draw(&mut frame);
+        let screen = Screen::compose(&frame, RenderMode::Normal);
+        assert_eq!(
+            screen.line(MARGIN_TOP).trim_end(),
+            "        Звук и музыка",
+            "Title is centered by characters."
+        );
+    }
+}
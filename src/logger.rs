@@ -0,0 +1,195 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::storage;
+
+/// Log in the config directory, every run appends to it. When it gets
+/// too big it's rotated: it becomes `snake.log.1`, older ones move to
+/// `snake.log.2` and so on, and only `KEEP` of them are kept.
+const LOG_FILE: &str = "snake.log";
+/// File is rotated when it would get bigger than this.
+pub const MAX_SIZE: u64 = 1024 * 1024;
+/// Rotated files kept besides the current one.
+pub const KEEP: usize = 3;
+/// Lines kept in memory for the debug overlay.
+const RECENT: usize = 20;
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// Terminal is taken by the game, so messages go to a file. The last
+/// few lines are kept in memory too, the debug overlay shows them.
+pub struct FileLogger {
+    level: LevelFilter,
+    path: PathBuf,
+    max_size: u64,
+    file: Mutex<Option<(File, u64)>>,
+    recent: Mutex<VecDeque<String>>,
+}
+
+impl FileLogger {
+    pub fn new(path: PathBuf, level: LevelFilter, max_size: u64) -> Self {
+        Self {
+            level,
+            path,
+            max_size,
+            file: Mutex::new(None),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Last lines, oldest first.
+    pub fn recent(&self) -> Vec<String> {
+        self.recent
+            .lock()
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// `snake.log` becomes `snake.log.1`, `snake.log.1` becomes
+    /// `snake.log.2`, the oldest one is gone.
+    fn rotate(&self) -> io::Result<()> {
+        for index in (1..KEEP).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                fs::rename(from, self.rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn write(&self, line: &str) -> io::Result<()> {
+        let mut file = self.file.lock().expect("Logger lock is never poisoned.");
+        let size = line.len() as u64 + 1;
+        if let Some((_, written)) = &*file {
+            if written + size > self.max_size {
+                *file = None;
+                self.rotate()?;
+            }
+        }
+        if file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let written = opened.metadata()?.len();
+            *file = Some((opened, written));
+        }
+        let (opened, written) = file.as_mut().expect("File is opened above.");
+        writeln!(opened, "{}", line)?;
+        *written += size;
+        Ok(())
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs_f64())
+            .unwrap_or_default();
+        let message = format!("{} {}", record.level(), record.args());
+        // Nowhere to report that the log can't be written
+        let _ = self.write(&format!("{:.3} {} {}", seconds, record.target(), message));
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT {
+                recent.pop_front();
+            }
+            recent.push_back(message);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some((file, _)) = file.as_mut() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Level by name as in `--log-level` and `SNAKE_LOG`.
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    name.parse().ok()
+}
+
+/// Installs the logger writing to the config directory, returns the path
+/// of the log. Nothing is logged when there is no config directory.
+pub fn init(level: LevelFilter) -> Option<&'static Path> {
+    let path = storage::config_file(LOG_FILE)?;
+    let logger = LOGGER.get_or_init(|| FileLogger::new(path, level, MAX_SIZE));
+    log::set_logger(logger).ok()?;
+    log::set_max_level(level);
+    Some(&logger.path)
+}
+
+/// Last lines of the installed logger, oldest first.
+pub fn recent() -> Vec<String> {
+    LOGGER.get().map(FileLogger::recent).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn log(logger: &FileLogger, level: Level, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("test")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("snake_log_{}", std::process::id()));
+        let logger = FileLogger::new(dir.join("snake.log"), LevelFilter::Info, 100);
+        for i in 0..20 {
+            log(&logger, Level::Info, &format!("line {}", i));
+        }
+        log(&logger, Level::Debug, "not logged");
+        logger.flush();
+        let current = fs::read_to_string(dir.join("snake.log")).unwrap();
+        assert!(current.ends_with("test INFO line 19\n"));
+        assert!(current.len() <= 100);
+        assert!(dir.join("snake.log.3").exists());
+        assert!(!dir.join("snake.log.4").exists(), "Only three are kept.");
+        let recent = logger.recent();
+        assert_eq!(recent.len(), RECENT);
+        assert_eq!(recent.last().map(String::as_str), Some("INFO line 19"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level("OFF"), Some(LevelFilter::Off));
+        assert_eq!(parse_level("loud"), None);
+    }
+}
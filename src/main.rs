@@ -10,148 +10,786 @@ use crossterm::{
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use rand::{thread_rng, Rng};
+use log::{error, info, LevelFilter};
+use rand::random;
+use snake::battlesnake::Battlesnake;
+use snake::bot::{Bot, Controller, GameState};
+use snake::cli::{self, Cli, Command, PlayOptions, TournamentOptions};
+use snake::debug::DebugOverlay;
+use snake::editor::Editor;
+use snake::events::{EventQueue, GameEvent};
+use snake::hud::HudWidget;
+use snake::level::Level;
+use snake::logger;
+use snake::replay::Replay;
+use snake::savegame::SavedGame;
+use snake::stats::{GameResult, Stats, StatsBook, StatsView};
+use snake::timer::FixedStep;
+use snake::tournament::{self, Format, Player, Tournament};
+use snake::{audio::Audio, audio_backend::SilentBackend, snake::Direction, sound_pack::SoundEvent};
+use snake::{env::EnvConfig, Size};
 use snake::{
-    apple::AppleDispencer,
-    frame::{new_frame, Drawable, Frame},
+    frame::{draw_hud, new_frame, Drawable, Frame},
+    hud::Hud,
+};
+use snake::{
+    menu::{Menu, MenuCommand},
+    snake::{Controls, Snake, Turn},
+};
+use snake::{
+    render::{self, Backend, CrosstermBackend},
+    screen::{RenderMode, Screen},
+    settings::Settings,
+    topbar::TopBar,
 };
-use snake::{audio::Audio, snake::Direction};
-use snake::{menu::Menu, snake::Snake};
-use snake::{render, topbar::TopBar};
 use std::{
-    error::Error,
+    env,
+    fmt::Display,
     io,
-    sync::mpsc::{self, Receiver},
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
     thread,
     time::{Duration, Instant},
 };
 
-static AUDIO_DIR: &str = "audio";
+static MENU_MUSIC: &str = "menu";
+static GAME_MUSIC: &str = "game";
 const MAX_APPLES: u8 = 3;
 const SPEED: u64 = 300;
+const BOT_TIMEOUT: u64 = 50;
+/// Default timeout of Battlesnake games.
+const BATTLESNAKE_TIMEOUT: u64 = 500;
+/// Game is simulated in steps of this length, every speed is a multiple
+/// of it.
+const SIM_STEP: Duration = Duration::from_millis(10);
+/// Menus are text lines placed in board columns, packing or widening the
+/// cells would move them, so they are always shown normally.
+const TEXT_MODE: RenderMode = RenderMode::Normal;
+/// Longest wait for a key in the menu, music is updated at least this often.
+const MENU_IDLE: Duration = Duration::from_millis(50);
 
-fn render_screen(render_rx: Receiver<Frame>) {
-    let mut last_frame = new_frame();
-    let mut stdout = io::stdout();
-    render::render(&mut stdout, &last_frame, &last_frame, true);
-    while let Ok(curr_frame) = render_rx.recv() {
-        render::render(&mut stdout, &last_frame, &curr_frame, false);
-        last_frame = curr_frame;
+/// Board is centered on the whole terminal, when size can't be read the
+/// layout is as small as possible.
+fn layout(frame: &Frame, mode: RenderMode) -> Screen {
+    let size = match terminal::size() {
+        Ok((cols, rows)) => (cols as usize, rows as usize),
+        Err(_) => Screen::fit_size(frame, mode),
+    };
+    Screen::layout(frame, mode, size)
+}
+
+/// Cells written for the last screen are stored in `diff_cells` for the
+/// debug overlay.
+fn render_screen(render_rx: Receiver<Screen>, diff_cells: Arc<AtomicUsize>) -> io::Result<()> {
+    let mut last_screen = layout(&new_frame(), RenderMode::Normal);
+    let mut backend = CrosstermBackend::new(io::stdout());
+    render::render(&mut backend, &last_screen, &last_screen, true)?;
+    while let Ok(curr_screen) = render_rx.recv() {
+        let written = render::render(&mut backend, &last_screen, &curr_screen, false)?;
+        diff_cells.store(written, Ordering::Relaxed);
+        last_screen = curr_screen;
     }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut audio = Audio::new();
-    audio.init(AUDIO_DIR);
+fn clear_screen<B: Backend>(backend: &mut B) -> io::Result<()> {
+    let blank = layout(&new_frame(), TEXT_MODE);
+    render::render(backend, &blank, &blank, true)?;
+    Ok(())
+}
 
-    // Terminal
-    let mut stdout = std::io::stdout();
+/// Raw mode and alternate screen for the time `f` runs, the terminal is
+/// restored even if it fails, error is returned after.
+fn in_terminal<T>(f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    let mut stdout = io::stdout();
     terminal::enable_raw_mode()?;
     stdout.execute(EnterAlternateScreen)?;
     stdout.execute(Hide)?;
+    let result = f();
+    stdout.execute(Show)?;
+    stdout.execute(LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn fail(message: impl Display) -> ! {
+    eprintln!("snake: {}", message);
+    process::exit(1);
+}
+
+fn record_game(stats: &mut StatsBook, menu: &Menu, snake: &Snake, topbar: &TopBar) {
+    stats.record(
+        &menu.settings().profile,
+        &GameResult {
+            score: topbar.get_scores(),
+            time: topbar.get_time(),
+            apples: topbar.get_apples(),
+            length: snake.length(),
+            speed: snake.speed(),
+            death: snake.death_cause(),
+        },
+    );
+    stats.save();
+}
+
+fn save_replay(replay: &Option<Replay>) {
+    if let (Some(replay), Some(path)) = (replay, Replay::last_path()) {
+        if let Err(e) = replay.save(&path) {
+            error!("Can't save replay {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn apply_volume(audio: &mut Audio, menu: &Menu) {
+    let settings = menu.settings();
+    audio.set_volume(settings.sfx_level(), settings.music_level());
+}
+
+/// Writes settings as soon as they change, so an error or a crash later
+/// doesn't lose them. Settings changed for one scripted game are not kept.
+fn save_settings(menu: &Menu, saved: &mut Settings, setup: &GameSetup) {
+    if setup.exit_after || menu.settings() == saved {
+        return;
+    }
+    menu.settings().save();
+    *saved = menu.settings().clone();
+}
+
+/// Turn of the snake for the key, relative controls use only Left and
+/// Right.
+fn key_turn(code: KeyCode, controls: Controls, snake: &Snake) -> Option<Direction> {
+    match (controls, code) {
+        (Controls::Arrows, code) => key_direction(code),
+        (Controls::Relative, KeyCode::Left) => Some(snake.heading().turned(Turn::Left)),
+        (Controls::Relative, KeyCode::Right) => Some(snake.heading().turned(Turn::Right)),
+        (Controls::Relative, _) => None,
+    }
+}
+
+fn key_direction(code: KeyCode) -> Option<Direction> {
+    match code {
+        KeyCode::Left => Some(Direction::Left),
+        KeyCode::Right => Some(Direction::Right),
+        KeyCode::Up => Some(Direction::Up),
+        KeyCode::Down => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+fn print_scores(stats: &Stats) {
+    if stats.scores.is_empty() {
+        println!("No high scores yet.");
+        return;
+    }
+    println!("{:>2} {:>6} {:>6} {:>6}", "#", "Score", "Time", "Speed");
+    for (place, entry) in stats.scores.iter().enumerate() {
+        println!(
+            "{:>2} {:>6} {:>6} {:>6}",
+            place + 1,
+            entry.score,
+            entry.time,
+            entry.speed
+        );
+    }
+}
+
+/// Game from `snake play` or from the menu, scripted play skips the menu
+/// and the program exits with the end of the game.
+struct GameSetup {
+    level: Level,
+    seed: Option<u64>,
+    speed: u64,
+    apples: u8,
+    /// Plays instead of the keyboard.
+    bot: Option<Box<dyn Controller>>,
+    exit_after: bool,
+}
+
+impl GameSetup {
+    fn menu() -> Self {
+        Self {
+            level: Level::default(),
+            seed: None,
+            speed: SPEED,
+            apples: MAX_APPLES,
+            bot: None,
+            exit_after: false,
+        }
+    }
+
+    /// Level is loaded and bot is started before the terminal is taken,
+    /// so errors are printed as usual.
+    fn play(options: &PlayOptions) -> Self {
+        let level = load_level(options.level.as_deref(), options.size);
+        let speed = options.speed.unwrap_or(SPEED);
+        // Bot is asked in the game loop, a longer wait would freeze the game
+        let timeout =
+            |default| Duration::from_millis(options.bot_timeout.unwrap_or(default).min(speed));
+        let bot: Option<Box<dyn Controller>> = if let Some(command) = &options.bot {
+            let bot = Bot::spawn(command, timeout(BOT_TIMEOUT))
+                .unwrap_or_else(|e| fail(format!("can't start bot '{}': {}", command, e)));
+            Some(Box::new(bot))
+        } else if let Some(url) = &options.battlesnake {
+            let server = Battlesnake::new(url, timeout(BATTLESNAKE_TIMEOUT))
+                .unwrap_or_else(|e| fail(format!("can't use Battlesnake server: {}", e)));
+            Some(Box::new(server))
+        } else {
+            None
+        };
+        Self {
+            level,
+            seed: options.seed,
+            speed,
+            apples: options.apples.unwrap_or(MAX_APPLES),
+            bot,
+            exit_after: true,
+        }
+    }
+}
+
+fn load_level(path: Option<&Path>, size: Option<Size>) -> Level {
+    match path {
+        Some(path) => Level::load(path)
+            .unwrap_or_else(|e| fail(format!("can't load level {}: {}", path.display(), e))),
+        None => Level::empty(size.unwrap_or_default()),
+    }
+}
+
+/// Plays the tournament without the terminal, standings go to stdout.
+fn run_tournament(options: TournamentOptions) -> io::Result<()> {
+    let threads = options
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+    let tournament = Tournament {
+        players: options
+            .players
+            .iter()
+            .map(|spec| Player::parse(spec))
+            .collect(),
+        format: match options.swiss {
+            Some(rounds) => Format::Swiss { rounds },
+            None => Format::RoundRobin,
+        },
+        games: options.games,
+        seed: options.seed.unwrap_or_else(random),
+        threads,
+        game: EnvConfig {
+            level: load_level(options.level.as_deref(), options.size),
+            apples: options.apples.unwrap_or(MAX_APPLES),
+            ..Default::default()
+        },
+        bot_timeout: Duration::from_millis(options.bot_timeout.unwrap_or(BOT_TIMEOUT)),
+        battlesnake_timeout: Duration::from_millis(
+            options.bot_timeout.unwrap_or(BATTLESNAKE_TIMEOUT),
+        ),
+    };
+    let standings = tournament.run()?;
+    if options.json {
+        let json = serde_json::to_string_pretty(&standings).map_err(io::Error::from)?;
+        println!("{}", json);
+    } else {
+        print!("{}", tournament::to_csv(&standings));
+    }
+    Ok(())
+}
+
+/// Menu and the game, returns last score and time.
+fn run(mut audio: Audio, settings: Settings, mut setup: GameSetup) -> io::Result<(usize, usize)> {
+    let mut stats = StatsBook::load();
+    let mut show_stats = false;
+    let mut backend = CrosstermBackend::new(io::stdout());
 
     // Render loop in a separate thread
     let (render_tx, render_rx) = mpsc::channel();
-    let render_handle = thread::spawn(move || {
-        render_screen(render_rx);
-    });
+    let diff_cells = Arc::new(AtomicUsize::new(0));
+    let render_diff_cells = Arc::clone(&diff_cells);
+    let render_handle = thread::spawn(move || render_screen(render_rx, render_diff_cells));
+    let mut debug = DebugOverlay::new();
 
     // Gameloop
     let mut instant = Instant::now();
-    let mut menu = Menu::new(SPEED, MAX_APPLES);
+    let mut menu = Menu::new(setup.speed, setup.apples, settings);
+    menu.set_music_tracks(audio.music_tracks(), GAME_MUSIC);
+    if setup.exit_after {
+        menu.active = false;
+    } else {
+        menu.set_saved_game(SavedGame::exists());
+    }
+    let mut resumed = None;
+    let mut last_result = (0, 0);
+    apply_volume(&mut audio, &menu);
+    let mut saved_settings = menu.settings().clone();
 
-    audio.play("enter");
+    audio.play_event(SoundEvent::Start);
+    audio.play_music(MENU_MUSIC);
     'menuloop: loop {
         // Per-frame init
+        let delta = instant.elapsed();
+        instant = Instant::now();
+        audio.update(delta);
         let mut curr_frame = new_frame();
 
-        // Input hadleres for menu
-        while event::poll(Duration::default())? {
+        // Input hadleres for menu, sleeps until a key is pressed. Keys of
+        // a scripted game are left for it
+        let mut wait = if menu.active {
+            MENU_IDLE
+        } else {
+            Duration::ZERO
+        };
+        while event::poll(wait)? {
+            wait = Duration::ZERO;
             if let Event::Key(key_event) = event::read()? {
+                // Any key closes stats screen
+                if show_stats {
+                    show_stats = false;
+                    audio.play_event(SoundEvent::MenuNavigate);
+                    continue;
+                }
                 match key_event.code {
-                    KeyCode::Enter => {
-                        menu.active = false;
-                        render::render(&mut stdout, &curr_frame, &curr_frame, true);
+                    KeyCode::Enter => match menu.enter() {
+                        Some(MenuCommand::Stats) => {
+                            show_stats = true;
+                            audio.play_event(SoundEvent::MenuNavigate);
+                        }
+                        Some(MenuCommand::Quit) => break 'menuloop,
+                        Some(command) => {
+                            if command == MenuCommand::Continue {
+                                menu.set_saved_game(false);
+                                resumed = SavedGame::load();
+                                // Unreadable save is kept, it may be from a newer version
+                                if resumed.is_none() {
+                                    menu.show_notice("Saved game can't be loaded");
+                                    audio.play_event(SoundEvent::MenuNavigate);
+                                    continue;
+                                }
+                                SavedGame::delete();
+                            }
+                            menu.active = false;
+                            audio.play_event(SoundEvent::Start);
+                            clear_screen(&mut backend)?;
+                        }
+                        None => audio.play_event(SoundEvent::MenuNavigate),
+                    },
+                    KeyCode::Esc => {
+                        if !menu.back() {
+                            break 'menuloop;
+                        }
+                        audio.play_event(SoundEvent::MenuNavigate);
                     }
-                    KeyCode::Esc | KeyCode::Char('q') => {
+                    KeyCode::Char('q') => {
                         break 'menuloop;
                     }
-                    KeyCode::Up | KeyCode::Down => menu.switch_current_option(),
-                    KeyCode::Left => menu.decrease_current_option(),
-                    KeyCode::Right => menu.insrease_current_option(),
+                    KeyCode::Up => {
+                        menu.previous_option();
+                        audio.play_event(SoundEvent::MenuNavigate);
+                    }
+                    KeyCode::Down => {
+                        menu.next_option();
+                        audio.play_event(SoundEvent::MenuNavigate);
+                    }
+                    KeyCode::Left => {
+                        menu.decrease_current_option();
+                        audio.play_event(SoundEvent::MenuNavigate);
+                    }
+                    KeyCode::Right => {
+                        menu.insrease_current_option();
+                        audio.play_event(SoundEvent::MenuNavigate);
+                    }
+                    KeyCode::Char('m') => menu.toggle_mute(),
                     _ => {}
                 }
+                apply_volume(&mut audio, &menu);
+                save_settings(&menu, &mut saved_settings, &setup);
             }
         }
-        menu.draw(&mut curr_frame);
+        if show_stats {
+            let profile = &menu.settings().profile;
+            StatsView {
+                profile,
+                stats: &stats.profile(profile),
+            }
+            .draw(&mut curr_frame);
+        } else {
+            menu.draw(&mut curr_frame);
+        }
         if !menu.active {
-            let mut snake = Snake::new(menu.speed());
-            let mut apple_dispencer = AppleDispencer::new(menu.apples());
-            let mut topbar = TopBar::new();
+            // Continued game is not recorded, its start is not known
+            let (mut snake, mut apple_dispencer, mut topbar, mut replay) = match resumed.take() {
+                Some(SavedGame {
+                    mut snake,
+                    apples,
+                    topbar,
+                }) => {
+                    snake.set_turn_buffer(menu.settings().turn_buffer);
+                    (snake, apples, topbar, None)
+                }
+                None => {
+                    let replay = Replay::new(
+                        setup.level.clone(),
+                        menu.speed(),
+                        menu.apples(),
+                        setup.seed.unwrap_or_else(random),
+                        menu.settings().turn_buffer,
+                    );
+                    let (snake, apples, topbar) = replay.start();
+                    (snake, apples, topbar, Some(replay))
+                }
+            };
+            let level = snake.level().clone();
+            let mut events = EventQueue::new();
+            let score_to_beat = stats.profile(&menu.settings().profile).best_score();
+            let mut high_score_announced = false;
+            // Bot is asked before the first move and after every move
+            let mut ask_bot = true;
+            let mut tick = 0;
+            match menu.music() {
+                Some(track) => audio.play_music(track),
+                None => audio.stop_music(),
+            }
 
-            'gameloop: loop {
-                let delta = instant.elapsed();
-                instant = Instant::now();
-                curr_frame = new_frame();
+            let mut clock = FixedStep::new(SIM_STEP);
+            instant = Instant::now();
+            let mut last_draw = instant;
 
-                // Input handlers for the game
-                while event::poll(Duration::default())? {
+            'gameloop: loop {
+                // Sleeps until a key is pressed or the step where something
+                // changes by itself: the snake moves, an apple is placed or
+                // expires, a number in the HUD goes on. Bot and music fades
+                // want the next step.
+                let ahead = if ask_bot || audio.is_fading() {
+                    Duration::ZERO
+                } else {
+                    snake
+                        .time_to_move()
+                        .min(apple_dispencer.time_to_change())
+                        .min(topbar.time_to_next_second())
+                };
+                let mut wait = clock.until(ahead);
+                // Keys, resizing of the terminal and steps are drawn
+                let mut changed = false;
+                while event::poll(wait)? {
+                    wait = Duration::ZERO;
+                    changed = true;
                     if let Event::Key(key_event) = event::read()? {
+                        let controls = menu.settings().controls;
+                        if let Some(direction) = key_turn(key_event.code, controls, &snake) {
+                            if setup.bot.is_none() {
+                                snake.queue_turn(direction);
+                                if let Some(replay) = &mut replay {
+                                    replay.turn(direction);
+                                }
+                            }
+                            continue;
+                        }
                         match key_event.code {
                             KeyCode::Esc | KeyCode::Char('q') => {
-                                audio.play("lose_sound");
+                                if let Some(bot) = &mut setup.bot {
+                                    bot.finish(&GameState::new(
+                                        tick,
+                                        &snake,
+                                        &apple_dispencer,
+                                        &topbar,
+                                    ));
+                                }
+                                save_replay(&replay);
+                                last_result = (topbar.get_scores(), topbar.get_time());
+                                if menu.settings().save_on_quit {
+                                    SavedGame {
+                                        snake,
+                                        apples: apple_dispencer,
+                                        topbar,
+                                    }
+                                    .save();
+                                    menu.set_saved_game(true);
+                                } else {
+                                    record_game(&mut stats, &menu, &snake, &topbar);
+                                }
+                                audio.play_event(SoundEvent::Die);
+                                if setup.exit_after {
+                                    break 'menuloop;
+                                }
+                                audio.play_music(MENU_MUSIC);
                                 menu.active = true;
-                                render::render(&mut stdout, &curr_frame, &curr_frame, true);
+                                clear_screen(&mut backend)?;
                                 break 'gameloop;
                             }
-                            KeyCode::Left => snake.turn_if_possible(Direction::Left),
-                            KeyCode::Right => snake.turn_if_possible(Direction::Right),
-                            KeyCode::Up => snake.turn_if_possible(Direction::Up),
-                            KeyCode::Down => snake.turn_if_possible(Direction::Down),
+                            KeyCode::Char('m') => {
+                                menu.toggle_mute();
+                                apply_volume(&mut audio, &menu);
+                                save_settings(&menu, &mut saved_settings, &setup);
+                            }
+                            KeyCode::F(3) => debug.toggle(),
                             _ => {}
                         }
                     }
                 }
 
-                snake.update(delta, || audio.play("move"));
-                apple_dispencer.update(delta);
-                snake.check_if_ate_apple(&mut apple_dispencer, || {
-                    topbar.scores();
-                    let mut rng = thread_rng();
-                    if rng.gen() {
-                        audio.play("hrum");
-                    } else {
-                        audio.play("niam");
+                let steps = clock.advance(instant.elapsed());
+                instant = Instant::now();
+
+                // Turns are taken before the step they are recorded for
+                for _ in 0..steps {
+                    let mut delta = clock.step();
+                    if let Some(bot) = setup.bot.as_mut().filter(|_| ask_bot) {
+                        let state = GameState::new(tick, &snake, &apple_dispencer, &topbar);
+                        if let Some(direction) = bot.ask(&state) {
+                            snake.queue_turn(direction);
+                            if let Some(replay) = &mut replay {
+                                replay.turn(direction);
+                            }
+                        }
+                        tick += 1;
+                    }
+
+                    if let Some(replay) = &mut replay {
+                        delta = replay.frame(delta);
+                    }
+                    audio.update(delta);
+                    snake.update(delta, &mut events);
+                    topbar.update(delta);
+                    apple_dispencer.update(delta, &mut events);
+                    snake.check_if_ate_apple(&mut apple_dispencer, &mut events);
+                    ask_bot = events
+                        .events()
+                        .iter()
+                        .any(|event| matches!(event, GameEvent::Moved { .. }));
+                    events.dispatch(&mut [&mut audio, &mut topbar]);
+
+                    // Only first time in a game, and there should be something to beat
+                    if !high_score_announced
+                        && score_to_beat > 0
+                        && topbar.get_scores() > score_to_beat
+                    {
+                        high_score_announced = true;
+                        audio.play_event(SoundEvent::NewHighScore);
+                    }
+                    if snake.is_dead() {
+                        if let Some(bot) = &mut setup.bot {
+                            bot.finish(&GameState::new(tick, &snake, &apple_dispencer, &topbar));
+                        }
+                        save_replay(&replay);
+                        audio.play_music(MENU_MUSIC);
+                        record_game(&mut stats, &menu, &snake, &topbar);
+                        last_result = (topbar.get_scores(), topbar.get_time());
+                        if setup.exit_after {
+                            break 'menuloop;
+                        }
+                        menu.get_game_results(topbar.get_scores(), topbar.get_time());
+                        menu.active = true;
+                        continue 'menuloop;
                     }
-                });
-                if snake.is_dead() {
-                    audio.play("lose_sound");
-                    menu.get_game_results(topbar.get_scores(), topbar.get_time());
-                    menu.active = true;
-                    continue 'menuloop;
                 }
 
-                let drawables: Vec<&dyn Drawable> = vec![&snake, &apple_dispencer, &topbar];
+                if steps == 0 && !changed {
+                    continue;
+                }
+                curr_frame = Frame::with_size(level.size);
+                let hud = Hud {
+                    widgets: &menu.settings().hud,
+                    topbar: &topbar,
+                    snake: &snake,
+                    apples: &apple_dispencer,
+                    high_score: score_to_beat,
+                };
+                if debug.is_visible() {
+                    debug.set_diff_cells(diff_cells.load(Ordering::Relaxed));
+                    debug.set_log(logger::recent());
+                }
+                debug.frame(last_draw.elapsed(), instant.elapsed());
+                last_draw = Instant::now();
+                let drawables: Vec<&dyn Drawable> =
+                    vec![&level, &snake, &apple_dispencer, &hud, &debug];
                 for drawable in drawables {
                     drawable.draw(&mut curr_frame);
                 }
 
-                let _ = render_tx.send(curr_frame);
-                thread::sleep(Duration::from_millis(1));
-                continue;
+                let mode = menu.settings().render_mode;
+                let _ = render_tx.send(layout(&curr_frame, mode));
             }
         }
-        let _ = render_tx.send(curr_frame);
-        thread::sleep(Duration::from_millis(10));
-        continue;
+        let _ = render_tx.send(layout(&curr_frame, TEXT_MODE));
     }
 
     // Cleanup
     drop(render_tx);
-    render_handle.join().unwrap();
+    // Terminal is restored even if rendering failed, error is shown after
+    let rendered = render_handle.join().unwrap();
     audio.wait();
-    stdout.execute(Show)?;
-    stdout.execute(LeaveAlternateScreen)?;
-    terminal::disable_raw_mode()?;
-    Ok(())
+    rendered?;
+    Ok(last_result)
+}
+
+/// Plays recorded game in real time, any key stops it.
+fn watch_replay(replay: &Replay, mode: RenderMode, widgets: &[HudWidget]) -> io::Result<()> {
+    let mut backend = CrosstermBackend::new(io::stdout());
+    let (mut snake, mut apples, mut topbar) = replay.start();
+    let mut events = EventQueue::new();
+    let mut last_screen = layout(&Frame::with_size(replay.level.size), mode);
+    render::render(&mut backend, &last_screen, &last_screen, true)?;
+    let mut deadline = Instant::now();
+    let mut frames = replay.frames().peekable();
+    loop {
+        let mut frame = Frame::with_size(replay.level.size);
+        match frames.next() {
+            Some((delta, turns)) => {
+                for direction in turns {
+                    snake.queue_turn(direction);
+                }
+                snake.update(delta, &mut events);
+                topbar.update(delta);
+                apples.update(delta, &mut events);
+                snake.check_if_ate_apple(&mut apples, &mut events);
+                events.dispatch(&mut [&mut topbar]);
+                deadline += delta;
+            }
+            None => deadline = Instant::now() + Duration::from_secs(3600),
+        }
+        let hud = Hud {
+            widgets,
+            topbar: &topbar,
+            snake: &snake,
+            apples: &apples,
+            high_score: 0,
+        };
+        let drawables: [&dyn Drawable; 4] = [&replay.level, &snake, &apples, &hud];
+        for drawable in drawables {
+            drawable.draw(&mut frame);
+        }
+        if frames.peek().is_none() {
+            draw_hud(&mut frame, "Replay is over, press any key");
+        }
+        let screen = layout(&frame, mode);
+        render::render(&mut backend, &last_screen, &screen, false)?;
+        last_screen = screen;
+
+        while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(_) = event::read()? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn edit_level(path: &Path, mut editor: Editor, mode: RenderMode) -> io::Result<()> {
+    let mut backend = CrosstermBackend::new(io::stdout());
+    let mut last_screen = layout(&Frame::with_size(editor.level().size), mode);
+    render::render(&mut backend, &last_screen, &last_screen, true)?;
+    loop {
+        let mut frame = Frame::with_size(editor.level().size);
+        editor.draw(&mut frame);
+        let screen = layout(&frame, mode);
+        render::render(&mut backend, &last_screen, &screen, false)?;
+        last_screen = screen;
+
+        if let Event::Key(key_event) = event::read()? {
+            if let Some(direction) = key_direction(key_event.code) {
+                editor.move_cursor(direction);
+                continue;
+            }
+            match key_event.code {
+                KeyCode::Char(' ') => editor.toggle_wall(),
+                KeyCode::Char('s') => {
+                    editor.level().save(path)?;
+                    editor.saved();
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn main() {
+    let Cli {
+        command,
+        audio_dir,
+        sound_pack,
+        profile,
+        log_level,
+    } = cli::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("snake: {}\nTry 'snake --help' for more information.", e);
+        process::exit(2);
+    });
+    let log_level = log_level
+        .or_else(|| {
+            env::var("SNAKE_LOG")
+                .ok()
+                .and_then(|name| logger::parse_level(&name))
+        })
+        .unwrap_or(LevelFilter::Info);
+    if let Some(path) = logger::init(log_level) {
+        info!("Logging to {} at level {}", path.display(), log_level);
+    }
+    let mut settings = Settings::load();
+    if let Some(profile) = profile {
+        settings.profile = profile;
+    }
+    let result = match command {
+        Command::Help => {
+            print!("{}", cli::HELP);
+            Ok(())
+        }
+        Command::Scores => {
+            print_scores(&StatsBook::load().profile(&settings.profile));
+            Ok(())
+        }
+        Command::Stats => {
+            let stats = StatsBook::load().profile(&settings.profile);
+            let view = StatsView {
+                profile: &settings.profile,
+                stats: &stats,
+            };
+            view.lines().iter().for_each(|line| println!("{}", line));
+            Ok(())
+        }
+        Command::Tournament(options) => run_tournament(options),
+        Command::Replay(path) => {
+            let replay = Replay::load(&path)
+                .unwrap_or_else(|e| fail(format!("can't read replay {}: {}", path.display(), e)));
+            in_terminal(|| watch_replay(&replay, settings.render_mode, &settings.hud))
+        }
+        Command::Edit(path, size) => {
+            let level = if path.exists() {
+                Level::load(&path)
+                    .unwrap_or_else(|e| fail(format!("can't load level {}: {}", path.display(), e)))
+            } else {
+                Level::empty(size)
+            };
+            in_terminal(|| edit_level(&path, Editor::new(level), settings.render_mode))
+        }
+        Command::Menu | Command::Play(_) => {
+            let play = match command {
+                Command::Play(options) => Some(options),
+                _ => None,
+            };
+            let mut audio = match &play {
+                Some(options) if options.no_audio => Audio::with_backend(SilentBackend),
+                _ => Audio::new(),
+            };
+            if let Some(pack_dir) = sound_pack.or_else(|| settings.sound_pack.clone()) {
+                audio.load_pack(pack_dir);
+            }
+            if let Some(audio_dir) = audio_dir.or_else(|| settings.audio_dir.clone()) {
+                audio.init(audio_dir);
+            }
+            if let Some(mode) = play.as_ref().and_then(|options| options.mode) {
+                settings.render_mode = mode;
+            }
+            let scripted = play.is_some();
+            let setup = play.as_ref().map_or_else(GameSetup::menu, GameSetup::play);
+            in_terminal(|| run(audio, settings, setup)).map(|(score, time)| {
+                if scripted {
+                    println!("Score: {} Time: {}", score, time);
+                }
+            })
+        }
+    };
+    if let Err(e) = result {
+        fail(e);
+    }
 }
@@ -1,126 +1,351 @@
 use crate::{
     frame::{draw_text, Drawable},
+    hud::HudWidget,
+    list_menu::{ListMenu, MenuItem},
+    screen::RenderMode,
+    settings::{Settings, MAX_VOLUME},
+    snake::{Controls, TURN_BUFFER_LIMITS},
     Point,
 };
 
-const SPEED_LIMITS: (u64, u64) = (100, 700);
-const APPLE_LIMITS: (u8, u8) = (1, 10);
+pub const SPEED_LIMITS: (u64, u64) = (100, 700);
+pub const APPLE_LIMITS: (u8, u8) = (1, 10);
 
+const CONTINUE: &str = "continue";
+const START: &str = "start";
+const SPEED: &str = "speed";
+const APPLES: &str = "apples";
+const SOUND: &str = "sound";
+const MUSIC: &str = "music";
+const MASTER_VOLUME: &str = "master_volume";
+const SFX_VOLUME: &str = "sfx_volume";
+const MUSIC_VOLUME: &str = "music_volume";
+const RENDER_MODE: &str = "render_mode";
+const SAVE_ON_QUIT: &str = "save_on_quit";
+const TURN_BUFFER: &str = "turn_buffer";
+const CONTROLS: &str = "controls";
+const HUD: &str = "hud";
+const STATS: &str = "stats";
+const QUIT: &str = "quit";
+
+/// What main loop should do after Enter in the menu.
+#[derive(PartialEq, Eq, Debug)]
+pub enum MenuCommand {
+    Start,
+    Continue,
+    Stats,
+    Quit,
+}
+
+/// Main menu of the game, items are kept by `ListMenu` and copied into
+/// settings when changed.
 pub struct Menu {
-    speed_millis: u64,
-    max_apples: u8,
+    list: ListMenu,
+    settings: Settings,
     pub active: bool,
-    current_option: MenuOption,
     last_score: usize,
     last_time: usize,
+    /// Shown instead of the last game until the next game is over.
+    notice: Option<String>,
 }
 
-pub enum MenuOption {
-    Speed,
-    Apples,
+fn volume_item(id: &'static str, label: &str, value: u8) -> MenuItem {
+    MenuItem::range(id, label, value as i64, 0, MAX_VOLUME as i64, 1)
 }
 
 impl Menu {
-    pub fn new(speed_millis: u64, max_apples: u8) -> Self {
+    pub fn new(speed_millis: u64, max_apples: u8, settings: Settings) -> Self {
+        let mut list = ListMenu::new(vec![
+            MenuItem::action(CONTINUE, "Continue"),
+            MenuItem::action(START, "New game"),
+            MenuItem::range(
+                SPEED,
+                "Refresh rate",
+                speed_millis as i64,
+                SPEED_LIMITS.0 as i64,
+                SPEED_LIMITS.1 as i64,
+                50,
+            ),
+            MenuItem::range(
+                APPLES,
+                "Apples",
+                max_apples as i64,
+                APPLE_LIMITS.0 as i64,
+                APPLE_LIMITS.1 as i64,
+                1,
+            ),
+            MenuItem::submenu(
+                SOUND,
+                "Sound",
+                vec![
+                    MenuItem::choice(MUSIC, "Music", Vec::new(), 0),
+                    volume_item(MASTER_VOLUME, "Volume", settings.master_volume),
+                    volume_item(SFX_VOLUME, "Effects", settings.sfx_volume),
+                    volume_item(MUSIC_VOLUME, "Music vol", settings.music_volume),
+                ],
+            ),
+            MenuItem::choice(
+                RENDER_MODE,
+                "Cells",
+                RenderMode::ALL
+                    .iter()
+                    .map(|mode| String::from(mode.name()))
+                    .collect(),
+                RenderMode::ALL
+                    .iter()
+                    .position(|mode| *mode == settings.render_mode)
+                    .unwrap_or(0),
+            ),
+            MenuItem::submenu(
+                HUD,
+                "HUD",
+                HudWidget::ALL
+                    .iter()
+                    .map(|w| MenuItem::toggle(w.id(), w.label(), settings.hud.contains(w)))
+                    .collect(),
+            ),
+            MenuItem::choice(
+                CONTROLS,
+                "Controls",
+                Controls::ALL
+                    .iter()
+                    .map(|controls| String::from(controls.name()))
+                    .collect(),
+                Controls::ALL
+                    .iter()
+                    .position(|controls| *controls == settings.controls)
+                    .unwrap_or(0),
+            ),
+            MenuItem::range(
+                TURN_BUFFER,
+                "Turn buffer",
+                settings.turn_buffer as i64,
+                TURN_BUFFER_LIMITS.0 as i64,
+                TURN_BUFFER_LIMITS.1 as i64,
+                1,
+            ),
+            MenuItem::toggle(SAVE_ON_QUIT, "Save on quit", settings.save_on_quit),
+            MenuItem::action(STATS, &format!("Stats: {}", settings.profile)),
+            MenuItem::action(QUIT, "Quit"),
+        ]);
+        list.set_hidden(CONTINUE, true);
         Self {
-            speed_millis,
-            max_apples,
+            list,
+            settings,
             active: true,
-            current_option: MenuOption::Speed,
             last_time: 0,
             last_score: 0,
+            notice: None,
         }
     }
 
+    pub fn next_option(&mut self) {
+        self.list.down();
+    }
+    pub fn previous_option(&mut self) {
+        self.list.up();
+    }
     pub fn insrease_current_option(&mut self) {
-        match self.current_option {
-            MenuOption::Speed => self.increase_speed(),
-            MenuOption::Apples => self.increase_apples(),
-        }
+        self.list.right();
+        self.sync_settings();
     }
     pub fn decrease_current_option(&mut self) {
-        match self.current_option {
-            MenuOption::Speed => self.decrease_speed(),
-            MenuOption::Apples => self.decrease_apples(),
-        }
+        self.list.left();
+        self.sync_settings();
+    }
+    pub fn enter(&mut self) -> Option<MenuCommand> {
+        let command = match self.list.enter() {
+            Some(CONTINUE) => Some(MenuCommand::Continue),
+            Some(START) => Some(MenuCommand::Start),
+            Some(STATS) => Some(MenuCommand::Stats),
+            Some(QUIT) => Some(MenuCommand::Quit),
+            _ => None,
+        };
+        self.sync_settings();
+        command
+    }
+    /// Leaves submenu, returns false when there is nothing to leave.
+    pub fn back(&mut self) -> bool {
+        self.list.back()
     }
-    pub fn switch_current_option(&mut self) {
-        self.current_option = match self.current_option {
-            MenuOption::Speed => MenuOption::Apples,
-            MenuOption::Apples => MenuOption::Speed,
+    // Items are copied into settings, main saves them when they change.
+    fn sync_settings(&mut self) {
+        let volume = |id| self.list.range_value(id).unwrap_or_default() as u8;
+        self.settings.master_volume = volume(MASTER_VOLUME);
+        self.settings.sfx_volume = volume(SFX_VOLUME);
+        self.settings.music_volume = volume(MUSIC_VOLUME);
+        self.settings.save_on_quit = self.list.toggle_value(SAVE_ON_QUIT).unwrap_or(true);
+        self.settings.turn_buffer = self.list.range_value(TURN_BUFFER).unwrap_or_default() as usize;
+        self.settings.render_mode = RenderMode::ALL
+            .into_iter()
+            .find(|mode| self.list.choice_value(RENDER_MODE) == Some(mode.name()))
+            .unwrap_or_default();
+        self.settings.controls = Controls::ALL
+            .into_iter()
+            .find(|controls| self.list.choice_value(CONTROLS) == Some(controls.name()))
+            .unwrap_or_default();
+        self.settings.hud = HudWidget::ALL
+            .into_iter()
+            .filter(|w| self.list.toggle_value(w.id()) == Some(true))
+            .collect();
+    }
+    /// "Continue" is there only while there is a saved game,
+    /// it is selected right away as most likely choice.
+    pub fn set_saved_game(&mut self, saved_game: bool) {
+        self.list.set_hidden(CONTINUE, !saved_game);
+        if saved_game {
+            self.list.select(CONTINUE);
         }
     }
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+    }
+    /// Tracks to choose in-game music from, starts from the one
+    /// with given name if it is there.
+    pub fn set_music_tracks(&mut self, tracks: Vec<String>, selected: &str) {
+        let selected = tracks.iter().position(|t| t == selected).unwrap_or(0);
+        self.list.set_choices(MUSIC, tracks, selected);
+    }
+    pub fn music(&self) -> Option<&str> {
+        self.list.choice_value(MUSIC)
+    }
     pub fn apples(&self) -> u8 {
-        self.max_apples
+        self.list.range_value(APPLES).unwrap_or_default() as u8
     }
     pub fn speed(&self) -> u64 {
-        self.speed_millis
+        self.list.range_value(SPEED).unwrap_or_default() as u64
     }
     pub fn get_game_results(&mut self, scores: usize, time: usize) {
         self.last_score = scores;
         self.last_time = time;
+        self.notice = None;
     }
-    fn increase_speed(&mut self) {
-        let new_speed = self.speed_millis + 50;
-        if new_speed <= SPEED_LIMITS.1 {
-            self.speed_millis = new_speed;
-        }
-    }
-
-    fn increase_apples(&mut self) {
-        let new_max_apples = self.max_apples + 1;
-        if new_max_apples <= APPLE_LIMITS.1 {
-            self.max_apples = new_max_apples;
-        }
-    }
-
-    fn decrease_speed(&mut self) {
-        let new_speed = self.speed_millis - 50;
-        if new_speed >= SPEED_LIMITS.0 {
-            self.speed_millis = new_speed;
-        }
+    pub fn show_notice(&mut self, notice: &str) {
+        self.notice = Some(String::from(notice));
     }
+}
 
-    fn decrease_apples(&mut self) {
-        let new_max_apples = self.max_apples - 1;
-        if new_max_apples >= APPLE_LIMITS.0 {
-            self.max_apples = new_max_apples;
-        }
-    }
+fn draw_centered(frame: &mut crate::frame::Frame, y: usize, text: &str) {
+    let text: String = text.chars().take(frame.cols()).collect();
+    let x = (frame.cols() - text.chars().count()) / 2;
+    draw_text(frame, Point::new(x, y), &text);
 }
-// Depending on current option we put selection and
-// try format it some normal view
+
+// Items are drawn by the list, here only what is around it
 impl Drawable for Menu {
     fn draw(&self, frame: &mut crate::frame::Frame) {
-        // Last game scores and time
-        if self.last_score > 0 {
-            draw_text(
+        let rows = frame.rows();
+        // Notice or last game scores and time
+        if let Some(notice) = &self.notice {
+            draw_centered(frame, 1, notice);
+        } else if self.last_score > 0 {
+            draw_centered(
                 frame,
-                Point::new(1, 1),
+                1,
                 format!("Last score: {} Time: {}", self.last_score, self.last_time).as_str(),
             );
         }
+        self.list.draw(frame);
+        if self.settings.muted {
+            draw_centered(frame, rows - 4, "Muted, press M");
+        }
+        let hint = match self.list.selected_id() {
+            Some(CONTINUE) => "Press Enter to Continue",
+            Some(START) => "Press Enter to Start",
+            Some(STATS) => "Press Enter to See Stats",
+            Some(SOUND) | Some(HUD) => "Press Enter to Open",
+            Some(QUIT) => "Press Enter to Quit",
+            _ if self.list.open_submenu().is_some() => "Esc to go back",
+            _ => "Left/Right to change",
+        };
+        draw_centered(frame, rows - 2, hint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::new_frame, screen::Screen};
 
-        // Speed and Apples options
-        draw_text(frame, Point::new(10, 10), "Refresh rate:");
-        let mut speed_option = format!("  {:^6}  ", self.speed_millis);
-        let mut apple_option = format!("  {:^6}  ", self.max_apples);
-        match self.current_option {
-            MenuOption::Speed => speed_option = format!("< {:^6} >", self.speed_millis),
-            MenuOption::Apples => apple_option = format!("< {:^6} >", self.max_apples),
+    #[test]
+    fn test_continue_option() {
+        let mut menu = Menu::new(300, 3, Settings::default());
+        for _ in 0..10 {
+            menu.next_option();
+            assert_ne!(
+                menu.list.selected_id(),
+                Some(CONTINUE),
+                "No continue without saved game."
+            );
         }
+        menu.set_saved_game(true);
+        assert_eq!(menu.enter(), Some(MenuCommand::Continue));
+        menu.set_saved_game(false);
+        assert_eq!(menu.list.selected_id(), Some(START));
+        assert_eq!(menu.enter(), Some(MenuCommand::Start));
+    }
 
-        draw_text(frame, Point::new(10, 11), speed_option.as_str());
-        draw_text(
-            frame,
-            Point::new(10, 12),
-            format!("{:^6}", "Apples:").as_str(),
-        );
-        draw_text(frame, Point::new(10, 13), apple_option.as_str());
-        draw_text(
-            frame,
-            Point::new(6, 14),
-            format!("{:^6}", "Press Enter to Start").as_str(),
+    #[test]
+    fn test_notice() {
+        let mut menu = Menu::new(300, 3, Settings::default());
+        menu.get_game_results(5, 20);
+        menu.show_notice("Saved game can't be loaded");
+        let mut frame = new_frame();
+        menu.draw(&mut frame);
+        let screen = Screen::compose(&frame, RenderMode::Normal);
+        assert!(screen.line(1).contains("Saved game can't be loaded"));
+
+        menu.get_game_results(6, 30);
+        let mut frame = new_frame();
+        menu.draw(&mut frame);
+        let screen = Screen::compose(&frame, RenderMode::Normal);
+        assert!(screen.line(1).contains("Last score: 6"));
+    }
+
+    #[test]
+    fn test_settings_from_items() {
+        let mut menu = Menu::new(300, 3, Settings::default());
+        menu.list.select(SAVE_ON_QUIT);
+        menu.insrease_current_option();
+        assert!(!menu.settings().save_on_quit);
+
+        menu.list.select(SOUND);
+        menu.enter();
+        menu.next_option();
+        menu.decrease_current_option();
+        assert_eq!(menu.settings().master_volume, MAX_VOLUME - 1);
+        assert!(menu.back());
+        assert!(!menu.back());
+
+        menu.list.select(SPEED);
+        menu.decrease_current_option();
+        assert_eq!(menu.speed(), 250);
+
+        menu.list.select(TURN_BUFFER);
+        menu.insrease_current_option();
+        assert_eq!(menu.settings().turn_buffer, 3);
+
+        menu.list.select(CONTROLS);
+        menu.insrease_current_option();
+        assert_eq!(menu.settings().controls, Controls::Relative);
+    }
+
+    #[test]
+    fn test_hud_widgets() {
+        let mut menu = Menu::new(300, 3, Settings::default());
+        menu.list.select(HUD);
+        menu.enter();
+        menu.insrease_current_option();
+        assert!(!menu.settings().hud.contains(&HudWidget::Score));
+        menu.list.select(HudWidget::Seed.id());
+        menu.insrease_current_option();
+        assert_eq!(
+            menu.settings().hud.last(),
+            Some(&HudWidget::Seed),
+            "Turned on widget is shown."
         );
     }
 }
@@ -0,0 +1,242 @@
+use rodio::Sink;
+
+/// Sounds with higher priority take voices of the lower ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+pub trait Voice {
+    fn is_finished(&self) -> bool;
+    fn stop(&self);
+    fn set_volume(&self, volume: f32);
+}
+
+impl Voice for Sink {
+    fn is_finished(&self) -> bool {
+        self.empty()
+    }
+
+    fn stop(&self) {
+        Sink::stop(self);
+    }
+
+    fn set_volume(&self, volume: f32) {
+        Sink::set_volume(self, volume);
+    }
+}
+
+impl<V: Voice + ?Sized> Voice for Box<V> {
+    fn is_finished(&self) -> bool {
+        (**self).is_finished()
+    }
+
+    fn stop(&self) {
+        (**self).stop();
+    }
+
+    fn set_volume(&self, volume: f32) {
+        (**self).set_volume(volume);
+    }
+}
+
+struct Playing<V> {
+    voice: V,
+    priority: Priority,
+    order: u64,
+    gain: f32,
+}
+
+/// Small voice allocator for sound effects. Every sound gets its own voice
+/// and starts right away, nothing is queued. When all voices are busy new
+/// sound takes place of the oldest one with lowest priority, and if all of
+/// them are more important - new sound is just skipped.
+pub struct Mixer<V: Voice> {
+    voices: Vec<Playing<V>>,
+    max_voices: usize,
+    started: u64,
+    volume: f32,
+}
+
+impl<V: Voice> Mixer<V> {
+    pub fn new(max_voices: usize) -> Self {
+        Self {
+            voices: Vec::with_capacity(max_voices),
+            max_voices,
+            started: 0,
+            volume: 1.0,
+        }
+    }
+
+    /// Frees a voice for a sound with given priority, stealing one if needed.
+    /// Returns false when there is no voice the sound is allowed to take.
+    pub fn make_room(&mut self, priority: Priority) -> bool {
+        self.voices.retain(|playing| !playing.voice.is_finished());
+        if self.voices.len() < self.max_voices {
+            return true;
+        }
+        let victim = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter(|(_, playing)| playing.priority <= priority)
+            .min_by_key(|(_, playing)| (playing.priority, playing.order))
+            .map(|(index, _)| index);
+        match victim {
+            Some(index) => {
+                self.voices.remove(index).voice.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds started voice, gain is its own volume on top of mixer volume.
+    pub fn add(&mut self, voice: V, priority: Priority, gain: f32) {
+        voice.set_volume(self.volume * gain);
+        self.started += 1;
+        self.voices.push(Playing {
+            voice,
+            priority,
+            order: self.started,
+            gain,
+        });
+    }
+
+    pub fn active(&self) -> usize {
+        self.voices
+            .iter()
+            .filter(|playing| !playing.voice.is_finished())
+            .count()
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.active() == 0
+    }
+
+    pub fn max_voices(&self) -> usize {
+        self.max_voices
+    }
+
+    /// Changes volume of playing voices too, not only of the next ones.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.voices
+            .iter()
+            .for_each(|playing| playing.voice.set_volume(volume * playing.gain));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[derive(Clone)]
+    pub(crate) struct TestVoice {
+        pub name: &'static str,
+        pub finished: Rc<Cell<bool>>,
+        pub volume: Rc<Cell<f32>>,
+    }
+
+    impl TestVoice {
+        pub fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                finished: Rc::new(Cell::new(false)),
+                volume: Rc::new(Cell::new(1.0)),
+            }
+        }
+    }
+
+    impl Voice for TestVoice {
+        fn is_finished(&self) -> bool {
+            self.finished.get()
+        }
+
+        fn stop(&self) {
+            self.finished.set(true);
+        }
+
+        fn set_volume(&self, volume: f32) {
+            self.volume.set(volume);
+        }
+    }
+
+    fn play(mixer: &mut Mixer<TestVoice>, voice: &TestVoice, priority: Priority) -> bool {
+        if !mixer.make_room(priority) {
+            return false;
+        }
+        mixer.add(voice.clone(), priority, 1.0);
+        true
+    }
+
+    #[test]
+    fn test_free_voices_are_used_first() {
+        let mut mixer = Mixer::new(2);
+        let first = TestVoice::new("first");
+        let second = TestVoice::new("second");
+        assert!(play(&mut mixer, &first, Priority::Low));
+        assert!(play(&mut mixer, &second, Priority::Low));
+        assert_eq!(mixer.active(), 2, "Both sounds play at the same time.");
+        assert!(!first.is_finished() && !second.is_finished());
+    }
+
+    #[test]
+    fn test_steal_oldest_lowest_priority() {
+        let mut mixer = Mixer::new(3);
+        let eat = TestVoice::new("eat");
+        let old_move = TestVoice::new("old move");
+        let new_move = TestVoice::new("new move");
+        assert!(play(&mut mixer, &old_move, Priority::Low));
+        assert!(play(&mut mixer, &eat, Priority::Normal));
+        assert!(play(&mut mixer, &new_move, Priority::Low));
+
+        let death = TestVoice::new("death");
+        assert!(play(&mut mixer, &death, Priority::High));
+        assert!(old_move.is_finished(), "Oldest low priority sound stolen.");
+        assert!(!new_move.is_finished() && !eat.is_finished());
+        assert_eq!(mixer.active(), 3, "Never more voices than limit.");
+    }
+
+    #[test]
+    fn test_lower_priority_is_skipped() {
+        let mut mixer = Mixer::new(1);
+        let death = TestVoice::new("death");
+        assert!(play(&mut mixer, &death, Priority::High));
+        let step = TestVoice::new("move");
+        assert!(
+            !play(&mut mixer, &step, Priority::Low),
+            "Move can't interrupt death sound."
+        );
+        assert!(!death.is_finished());
+
+        death.finished.set(true);
+        assert!(
+            play(&mut mixer, &step, Priority::Low),
+            "Finished voice become free."
+        );
+        assert_eq!(mixer.voices[0].voice.name, "move");
+    }
+
+    #[test]
+    fn test_volume() {
+        let mut mixer = Mixer::new(2);
+        let first = TestVoice::new("first");
+        assert!(play(&mut mixer, &first, Priority::Low));
+        mixer.set_volume(0.5);
+        assert_eq!(first.volume.get(), 0.5, "Playing voice changed live.");
+        let second = TestVoice::new("second");
+        assert!(play(&mut mixer, &second, Priority::Low));
+        assert_eq!(second.volume.get(), 0.5, "New voice gets same volume.");
+
+        let quiet = TestVoice::new("quiet");
+        assert!(mixer.make_room(Priority::Low));
+        mixer.add(quiet.clone(), Priority::Low, 0.5);
+        assert_eq!(quiet.volume.get(), 0.25, "Own gain is applied too.");
+        mixer.set_volume(1.0);
+        assert_eq!(quiet.volume.get(), 0.5);
+    }
+}
@@ -0,0 +1,202 @@
+//! Music channel lives apart from the sound effects mixer. Only one track
+//! is wanted at a time, but while switching the old one fades out and the
+//! new one fades in, so for a moment both of them can be heard.
+
+use std::time::Duration;
+
+use crate::{mixer::Voice, timer::Timer};
+
+const CROSSFADE_TIME: Duration = Duration::from_millis(1500);
+const DUCK_FADE_TIME: Duration = Duration::from_millis(150);
+const DUCK_VOLUME: f32 = 0.25;
+
+struct Track<V> {
+    name: String,
+    voice: V,
+    level: f32,
+    target: f32,
+}
+
+pub struct Music<V: Voice> {
+    tracks: Vec<Track<V>>,
+    duck: Option<Timer>,
+    duck_level: f32,
+    volume: f32,
+}
+
+impl<V: Voice> Default for Music<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Voice> Music<V> {
+    pub fn new() -> Self {
+        Self {
+            tracks: Vec::new(),
+            duck: None,
+            duck_level: 1.0,
+            volume: 1.0,
+        }
+    }
+
+    /// Name of the track that is playing or fading in right now.
+    pub fn current(&self) -> Option<&str> {
+        self.tracks
+            .iter()
+            .find(|track| track.target > 0.0)
+            .map(|track| track.name.as_str())
+    }
+
+    /// Starts new looping voice from silence and fades out everything else.
+    pub fn switch(&mut self, name: &str, voice: V) {
+        self.fade_out();
+        voice.set_volume(0.0);
+        self.tracks.push(Track {
+            name: String::from(name),
+            voice,
+            level: 0.0,
+            target: 1.0,
+        });
+    }
+
+    pub fn fade_out(&mut self) {
+        self.tracks.iter_mut().for_each(|track| track.target = 0.0);
+    }
+
+    /// Pulls music down for some time, for example under an important effect.
+    pub fn duck(&mut self, time: Duration) {
+        self.duck = Some(Timer::new(time));
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        if let Some(timer) = self.duck.as_mut() {
+            timer.tick(delta);
+            if timer.finished() {
+                self.duck = None;
+            }
+        }
+        let duck_target = if self.duck.is_some() {
+            DUCK_VOLUME
+        } else {
+            1.0
+        };
+        self.duck_level = approach(self.duck_level, duck_target, step(delta, DUCK_FADE_TIME));
+
+        let fade_step = step(delta, CROSSFADE_TIME);
+        for track in self.tracks.iter_mut() {
+            track.level = approach(track.level, track.target, fade_step);
+        }
+        self.apply_volume();
+        self.tracks.retain(|track| {
+            let faded_out = track.target == 0.0 && track.level == 0.0;
+            if faded_out {
+                track.voice.stop();
+            }
+            !faded_out
+        });
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        for track in self.tracks.iter() {
+            track
+                .voice
+                .set_volume(track.level * self.duck_level * self.volume);
+        }
+    }
+
+    /// Volume is still moving, `update` should be called often.
+    pub fn is_fading(&self) -> bool {
+        self.duck.is_some()
+            || self.duck_level < 1.0
+            || self.tracks.iter().any(|track| track.level != track.target)
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.tracks.is_empty()
+    }
+}
+
+fn step(delta: Duration, full_time: Duration) -> f32 {
+    delta.as_secs_f32() / full_time.as_secs_f32()
+}
+
+fn approach(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mixer::tests::TestVoice;
+
+    #[test]
+    fn test_crossfade() {
+        let mut music = Music::new();
+        let menu = TestVoice::new("menu");
+        music.switch("menu", menu.clone());
+        assert_eq!(menu.volume.get(), 0.0, "New track starts silent.");
+        music.update(CROSSFADE_TIME);
+        assert_eq!(menu.volume.get(), 1.0, "Faded in after crossfade time.");
+
+        let game = TestVoice::new("game");
+        music.switch("game", game.clone());
+        assert_eq!(music.current(), Some("game"));
+        music.update(CROSSFADE_TIME / 2);
+        assert!((menu.volume.get() - 0.5).abs() < 0.01);
+        assert!((game.volume.get() - 0.5).abs() < 0.01);
+
+        music.update(CROSSFADE_TIME / 2);
+        assert!(menu.finished.get(), "Old track stopped after fade out.");
+        assert_eq!(game.volume.get(), 1.0);
+        assert_eq!(music.tracks.len(), 1);
+    }
+
+    #[test]
+    fn test_duck() {
+        let mut music = Music::new();
+        let game = TestVoice::new("game");
+        music.switch("game", game.clone());
+        music.update(CROSSFADE_TIME);
+
+        music.duck(Duration::from_secs(1));
+        music.update(DUCK_FADE_TIME);
+        assert_eq!(game.volume.get(), DUCK_VOLUME, "Music is ducked.");
+        music.update(Duration::from_secs(1));
+        music.update(DUCK_FADE_TIME);
+        assert_eq!(game.volume.get(), 1.0, "Music is back after ducking.");
+    }
+
+    #[test]
+    fn test_fade_out() {
+        let mut music = Music::new();
+        let game = TestVoice::new("game");
+        music.switch("game", game.clone());
+        music.fade_out();
+        assert_eq!(music.current(), None);
+        music.update(CROSSFADE_TIME);
+        assert!(music.is_silent());
+        assert!(game.finished.get());
+    }
+
+    #[test]
+    fn test_volume() {
+        let mut music = Music::new();
+        let game = TestVoice::new("game");
+        music.switch("game", game.clone());
+        music.update(CROSSFADE_TIME);
+        music.set_volume(0.5);
+        assert_eq!(game.volume.get(), 0.5, "Volume applied immediately.");
+        music.update(CROSSFADE_TIME);
+        assert_eq!(game.volume.get(), 0.5, "Volume stays after update.");
+    }
+}
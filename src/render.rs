@@ -1,26 +1,264 @@
-use crate::frame::Frame;
+use crate::screen::{Cell, Screen, BACKGROUND, FOREGROUND, OUTSIDE};
 use crossterm::{
     cursor::MoveTo,
-    style::{Color, SetBackgroundColor, SetForegroundColor},
+    style::{Color, Colors, SetColors},
     terminal::{Clear, ClearType},
     QueueableCommand,
 };
-use std::io::{Stdout, Write};
+use std::io::{self, BufWriter, Write};
 
-pub fn render(stdout: &mut Stdout, last_frame: &Frame, curr_frame: &Frame, force: bool) {
+/// Where frames end up. Render only decides which cells have changed,
+/// backend puts them on the terminal or, in tests, into memory, so
+/// drawing can be checked as plain text.
+pub trait Backend {
+    fn clear(&mut self) -> io::Result<()>;
+    fn move_to(&mut self, x: u16, y: u16) -> io::Result<()>;
+    fn set_colors(&mut self, fg: Color, bg: Color) -> io::Result<()>;
+    /// Writes at cursor position, cursor goes one cell right.
+    fn put(&mut self, c: char) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Everything goes through one buffer and reaches the terminal in a single
+/// write on flush, which matters a lot when playing over SSH.
+pub struct CrosstermBackend<W: Write> {
+    out: BufWriter<W>,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: BufWriter::new(out),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.out.get_ref()
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn clear(&mut self) -> io::Result<()> {
+        self.out
+            .queue(SetColors(Colors::new(FOREGROUND, OUTSIDE)))?;
+        self.out.queue(Clear(ClearType::All))?;
+        self.set_colors(FOREGROUND, BACKGROUND)
+    }
+
+    fn move_to(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.out.queue(MoveTo(x, y))?;
+        Ok(())
+    }
+
+    fn set_colors(&mut self, fg: Color, bg: Color) -> io::Result<()> {
+        self.out.queue(SetColors(Colors::new(fg, bg)))?;
+        Ok(())
+    }
+
+    fn put(&mut self, c: char) -> io::Result<()> {
+        let mut buf = [0; 4];
+        self.out.write_all(c.encode_utf8(&mut buf).as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Keeps screen in memory, as text or cell by cell with colors.
+pub struct MemoryBackend {
+    cells: Vec<Vec<Cell>>,
+    cursor: (usize, usize),
+    colors: (Color, Color),
+    drawn: usize,
+    moves: usize,
+}
+
+impl MemoryBackend {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cells: vec![vec![Cell::default(); cols]; rows],
+            cursor: (0, 0),
+            colors: (FOREGROUND, BACKGROUND),
+            drawn: 0,
+            moves: 0,
+        }
+    }
+
+    /// Screen content, trailing spaces of rows are dropped.
+    pub fn text(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.c).collect();
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y][x]
+    }
+
+    /// How many cells were drawn since creation.
+    pub fn drawn(&self) -> usize {
+        self.drawn
+    }
+
+    /// How many times cursor was moved since creation.
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn clear(&mut self) -> io::Result<()> {
+        for row in self.cells.iter_mut() {
+            row.fill(Cell::default());
+        }
+        self.colors = (FOREGROUND, BACKGROUND);
+        Ok(())
+    }
+
+    fn move_to(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.cursor = (x as usize, y as usize);
+        self.moves += 1;
+        Ok(())
+    }
+
+    fn set_colors(&mut self, fg: Color, bg: Color) -> io::Result<()> {
+        self.colors = (fg, bg);
+        Ok(())
+    }
+
+    fn put(&mut self, c: char) -> io::Result<()> {
+        let (x, y) = self.cursor;
+        let (fg, bg) = self.colors;
+        self.cells[y][x] = Cell { c, fg, bg };
+        self.cursor = (x + 1, y);
+        self.drawn += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Goes row by row, so changed cells next to each other are written as
+/// one run and cursor is moved only at the start of a run, colors are
+/// set only when they differ from previous cell. Screen of another size
+/// (render mode was changed) is drawn from scratch. Returns how many
+/// cells were written.
+pub fn render<B: Backend>(
+    backend: &mut B,
+    last_screen: &Screen,
+    curr_screen: &Screen,
+    force: bool,
+) -> io::Result<usize> {
+    let force = force
+        || last_screen.cols() != curr_screen.cols()
+        || last_screen.rows() != curr_screen.rows();
     if force {
-        stdout.queue(SetBackgroundColor(Color::Blue)).unwrap();
-        stdout.queue(Clear(ClearType::All)).unwrap();
-        stdout.queue(SetBackgroundColor(Color::Black)).unwrap();
-        stdout.queue(SetForegroundColor(Color::White)).unwrap();
-    }
-    for (x, col) in curr_frame.iter().enumerate() {
-        for (y, s) in col.iter().enumerate() {
-            if *s != last_frame[x][y] || force {
-                stdout.queue(MoveTo(x as u16, y as u16)).unwrap();
-                print!("{}", *s);
+        backend.clear()?;
+    }
+    // Where the cursor is after the last write and what colors are set,
+    // unknown at the start.
+    let mut cursor = None;
+    let mut colors = None;
+    let mut written = 0;
+    for y in 0..curr_screen.rows() {
+        for x in 0..curr_screen.cols() {
+            let cell = curr_screen.get(x, y);
+            if cell == last_screen.get(x, y) && !force {
+                continue;
+            }
+            if cursor != Some((x, y)) {
+                backend.move_to(x as u16, y as u16)?;
             }
+            if colors != Some((cell.fg, cell.bg)) {
+                backend.set_colors(cell.fg, cell.bg)?;
+                colors = Some((cell.fg, cell.bg));
+            }
+            backend.put(cell.c)?;
+            written += 1;
+            cursor = Some((x + 1, y));
         }
     }
-    stdout.flush().unwrap();
-}
\ No newline at end of file
+    backend.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::{draw_text, new_frame, Frame},
+        screen::RenderMode,
+        Point, NUM_COLS, NUM_ROWS,
+    };
+
+    fn screen(frame: &Frame) -> Screen {
+        Screen::compose(frame, RenderMode::Normal)
+    }
+
+    #[test]
+    fn test_only_changes_are_drawn() {
+        let mut backend = MemoryBackend::new(NUM_COLS, NUM_ROWS);
+        let last = screen(&new_frame());
+        render(&mut backend, &last, &last, true).unwrap();
+        assert_eq!(backend.drawn(), NUM_COLS * NUM_ROWS);
+        assert_eq!(backend.moves(), NUM_ROWS, "One move for each row.");
+
+        let mut curr = new_frame();
+        draw_text(&mut curr, Point::new(2, 1), "hi");
+        draw_text(&mut curr, Point::new(10, 1), "there");
+        let written = render(&mut backend, &last, &screen(&curr), false).unwrap();
+        assert_eq!(written, 7);
+        assert_eq!(backend.drawn(), NUM_COLS * NUM_ROWS + 7);
+        assert_eq!(backend.moves(), NUM_ROWS + 2, "One move for each run.");
+        assert_eq!(backend.text().lines().nth(1), Some("  hi      there"));
+    }
+
+    #[test]
+    fn test_terminal_output() {
+        let mut backend = CrosstermBackend::new(Vec::new());
+        let last = screen(&new_frame());
+        let mut curr = new_frame();
+        draw_text(&mut curr, Point::new(2, 1), "hi");
+        curr[4][3] = '⇑';
+        render(&mut backend, &last, &screen(&curr), false).unwrap();
+        assert_eq!(
+            String::from_utf8(backend.get_ref().clone()).unwrap(),
+            "\x1b[2;3H\x1b[38;5;15m\x1b[48;5;0mhi\x1b[4;5H⇑"
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut backend = MemoryBackend::new(NUM_COLS, NUM_ROWS / 2);
+        let last = Screen::compose(&new_frame(), RenderMode::HalfBlock);
+        let mut curr = new_frame();
+        curr[3][4] = 'Q';
+        curr[3][5] = 'X';
+        curr[4][5] = 'X';
+        render(
+            &mut backend,
+            &last,
+            &Screen::compose(&curr, RenderMode::HalfBlock),
+            false,
+        )
+        .unwrap();
+        let cell = backend.cell(3, 2);
+        assert_eq!(
+            (cell.c, cell.fg, cell.bg),
+            ('▀', Color::Red, Color::DarkGreen)
+        );
+        let cell = backend.cell(4, 2);
+        assert_eq!(
+            (cell.c, cell.fg, cell.bg),
+            ('▄', Color::DarkGreen, BACKGROUND)
+        );
+    }
+}
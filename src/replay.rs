@@ -0,0 +1,157 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apple::AppleDispencer,
+    level::Level,
+    snake::{Direction, Snake, TURN_BUFFER},
+    storage,
+    topbar::TopBar,
+};
+
+/// Last finished game is kept here, it can be watched with `snake replay`.
+const REPLAY_FILE: &str = "last_replay.json";
+
+/// Game is the same each time it's run with the same seed, level and
+/// input, so a replay keeps only those: length of every frame and turns
+/// pressed in it. Frame time is rounded to microseconds before the game
+/// uses it, then the replay runs exactly like the recorded game.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Replay {
+    pub level: Level,
+    pub speed: u64,
+    pub apples: u8,
+    pub seed: u64,
+    /// Same turns are taken only with the same buffer.
+    #[serde(default = "default_turn_buffer")]
+    pub turn_buffer: usize,
+    /// Length of every frame in microseconds.
+    deltas: Vec<u64>,
+    /// Index of the frame and turn taken before its update.
+    turns: Vec<(usize, Direction)>,
+}
+
+fn default_turn_buffer() -> usize {
+    TURN_BUFFER
+}
+
+impl Replay {
+    pub fn new(level: Level, speed: u64, apples: u8, seed: u64, turn_buffer: usize) -> Self {
+        Self {
+            level,
+            speed,
+            apples,
+            seed,
+            turn_buffer,
+            deltas: Vec::new(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// Snake, apples and scores the recorded game has started with.
+    pub fn start(&self) -> (Snake, AppleDispencer, TopBar) {
+        let mut snake = Snake::on_level(self.level.clone(), self.speed);
+        snake.set_turn_buffer(self.turn_buffer);
+        (
+            snake,
+            AppleDispencer::with_seed(self.apples, self.seed, self.level.clone()),
+            TopBar::new(),
+        )
+    }
+
+    /// Starts next frame, returns its time as the game should see it.
+    pub fn frame(&mut self, delta: Duration) -> Duration {
+        let micros = delta.as_micros() as u64;
+        self.deltas.push(micros);
+        Duration::from_micros(micros)
+    }
+
+    /// Turn before the next frame.
+    pub fn turn(&mut self, direction: Direction) {
+        self.turns.push((self.deltas.len(), direction));
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Time and turns of every frame, in order. Turns are recorded in
+    /// order too, so they are taken from the front while frame matches.
+    pub fn frames(&self) -> impl Iterator<Item = (Duration, Vec<Direction>)> + '_ {
+        let mut turns = self.turns.iter().peekable();
+        self.deltas.iter().enumerate().map(move |(index, micros)| {
+            let mut pressed = Vec::new();
+            while let Some((_, direction)) = turns.next_if(|(frame, _)| *frame == index) {
+                pressed.push(*direction);
+            }
+            (Duration::from_micros(*micros), pressed)
+        })
+    }
+
+    pub fn last_path() -> Option<PathBuf> {
+        storage::config_file(REPLAY_FILE)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        storage::load_json(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        storage::save_json(path, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventQueue;
+
+    // Plays the game the same way main loop does.
+    fn play(replay: &Replay) -> (Snake, AppleDispencer, TopBar) {
+        let (mut snake, mut apples, mut topbar) = replay.start();
+        let mut events = EventQueue::new();
+        for (delta, turns) in replay.frames() {
+            for direction in turns {
+                snake.queue_turn(direction);
+            }
+            snake.update(delta, &mut events);
+            topbar.update(delta);
+            apples.update(delta, &mut events);
+            snake.check_if_ate_apple(&mut apples, &mut events);
+            events.dispatch(&mut [&mut topbar]);
+        }
+        (snake, apples, topbar)
+    }
+
+    #[test]
+    fn test_replay_repeats_game() {
+        let mut replay = Replay::new(Level::default(), 100, 5, 99, 3);
+        let turns = [
+            Direction::Up,
+            Direction::Left,
+            Direction::Down,
+            Direction::Right,
+        ];
+        for i in 0..400 {
+            replay.frame(Duration::from_nanos(33_333_333 + i * 1000));
+            if i % 7 == 0 {
+                replay.turn(turns[i as usize / 7 % 4]);
+            }
+        }
+        let path = std::env::temp_dir().join(format!("snake_replay_{}.json", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(play(&loaded), play(&replay));
+    }
+}
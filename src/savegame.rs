@@ -0,0 +1,115 @@
+//! Game interrupted with `Esc` is written to `savegame.json` in the config
+//! directory and can be continued from the menu. Everything is kept as is, including timers of the snake and
+//! apples, so the run goes on exactly from the same moment.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{apple::AppleDispencer, snake::Snake, storage, topbar::TopBar};
+
+const SAVEGAME_FILE: &str = "savegame.json";
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct SavedGame {
+    pub snake: Snake,
+    pub apples: AppleDispencer,
+    pub topbar: TopBar,
+}
+
+impl SavedGame {
+    pub fn path() -> Option<PathBuf> {
+        storage::config_file(SAVEGAME_FILE)
+    }
+
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|path| path.exists())
+    }
+
+    pub fn load() -> Option<Self> {
+        Self::load_from(Self::path()?)
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Option<Self> {
+        if !path.as_ref().exists() {
+            return None;
+        }
+        storage::load_json(&path)
+            .map_err(|e| error!("Can't read saved game {}: {}", path.as_ref().display(), e))
+            .ok()
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = Self::path() {
+            self.save_to(path);
+        }
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) {
+        if let Err(e) = storage::save_json(&path, self) {
+            error!("Can't save game {}: {}", path.as_ref().display(), e);
+        }
+    }
+
+    /// Saved game is continued only once.
+    pub fn delete() {
+        if let Some(path) = Self::path() {
+            Self::delete_at(path);
+        }
+    }
+
+    pub fn delete_at<P: AsRef<Path>>(path: P) {
+        if path.as_ref().exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                error!("Can't remove saved game {}: {}", path.as_ref().display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use super::*;
+    use crate::{events::EventQueue, snake::Direction, Point};
+
+    fn play(game: &mut SavedGame, delta: Duration, events: &mut EventQueue) {
+        game.snake.update(delta, events);
+        game.apples.update(delta, events);
+        game.snake.check_if_ate_apple(&mut game.apples, events);
+        game.topbar.update(delta);
+        events.dispatch(&mut [&mut game.topbar]);
+    }
+
+    #[test]
+    fn test_save_and_continue() {
+        let path = env::temp_dir().join(format!("snake_savegame_{}.json", std::process::id()));
+        let mut events = EventQueue::new();
+        let mut game = SavedGame {
+            snake: Snake::new(100),
+            apples: AppleDispencer::new(3),
+            topbar: TopBar::new(),
+        };
+        play(&mut game, Duration::from_millis(150), &mut events);
+        game.snake.queue_turn(Direction::Up);
+        play(&mut game, Duration::from_millis(30), &mut events);
+
+        game.save_to(&path);
+        let mut restored = SavedGame::load_from(&path).expect("Game is saved.");
+        assert_eq!(restored, game);
+
+        // Snake timer goes on from saved value, it is not restarted.
+        restored
+            .snake
+            .update(Duration::from_millis(70), &mut events);
+        assert_eq!(restored.snake.head(), Point::new(21, 19));
+
+        SavedGame::delete_at(&path);
+        assert_eq!(SavedGame::load_from(&path), None);
+    }
+}
@@ -0,0 +1,394 @@
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::frame::Frame;
+
+/// Terminal cells are about twice as tall as wide, so the square board
+/// looks stretched. In double width mode every board cell takes two
+/// terminal columns and game objects get two-character glyphs. Half block
+/// mode goes the other way: one terminal row shows two board rows as
+/// colored `▀`/`▄` blocks, so twice bigger board fits. Text is never
+/// stretched, it starts at the column of its first cell.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    #[default]
+    Normal,
+    DoubleWidth,
+    HalfBlock,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 3] = [
+        RenderMode::Normal,
+        RenderMode::DoubleWidth,
+        RenderMode::HalfBlock,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderMode::Normal => "normal",
+            RenderMode::DoubleWidth => "double",
+            RenderMode::HalfBlock => "half",
+        }
+    }
+
+    /// Terminal columns for one board cell.
+    pub fn cell_width(&self) -> usize {
+        match self {
+            RenderMode::Normal | RenderMode::HalfBlock => 1,
+            RenderMode::DoubleWidth => 2,
+        }
+    }
+
+    /// Board rows in one terminal row.
+    pub fn cell_height(&self) -> usize {
+        match self {
+            RenderMode::Normal | RenderMode::DoubleWidth => 1,
+            RenderMode::HalfBlock => 2,
+        }
+    }
+}
+
+pub const FOREGROUND: Color = Color::White;
+pub const BACKGROUND: Color = Color::Black;
+/// Everything around the board.
+pub const OUTSIDE: Color = Color::Blue;
+/// Spaces between HUD items on one line.
+const HUD_GAP: usize = 2;
+
+/// One terminal cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub c: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Cell {
+    pub fn new(c: char) -> Self {
+        Self {
+            c,
+            fg: FOREGROUND,
+            bg: BACKGROUND,
+        }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new(' ')
+    }
+}
+
+fn glyph_pair(c: char) -> [char; 2] {
+    match c {
+        '⇐' => ['<', '='],
+        '⇒' => ['=', '>'],
+        '⇑' => ['/', '\\'],
+        '⇓' => ['\\', '/'],
+        'X' => ['[', ']'],
+        'Q' => ['(', ')'],
+        '#' => ['#', '#'],
+        c => [c, ' '],
+    }
+}
+
+/// Palette of half block mode, empty cell has no color.
+fn glyph_color(c: char) -> Option<Color> {
+    match c {
+        ' ' => None,
+        '⇐' | '⇒' | '⇑' | '⇓' => Some(Color::Green),
+        'X' => Some(Color::DarkGreen),
+        'Q' => Some(Color::Red),
+        '#' => Some(Color::Grey),
+        _ => Some(FOREGROUND),
+    }
+}
+
+fn half_block(top: char, bottom: char) -> Cell {
+    let (c, fg, bg) = match (glyph_color(top), glyph_color(bottom)) {
+        (None, None) => (' ', FOREGROUND, BACKGROUND),
+        (Some(top), None) => ('▀', top, BACKGROUND),
+        (None, Some(bottom)) => ('▄', bottom, BACKGROUND),
+        (Some(top), Some(bottom)) if top == bottom => ('█', top, BACKGROUND),
+        (Some(top), Some(bottom)) => ('▀', top, bottom),
+    };
+    Cell { c, fg, bg }
+}
+
+/// What is actually shown on the terminal, frame composed for a mode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![Cell::default(); cols * rows],
+        }
+    }
+
+    /// Board in a box, centered on the terminal of given size, and HUD
+    /// lines above it. What doesn't fit is cut off.
+    pub fn layout(frame: &Frame, mode: RenderMode, (cols, rows): (usize, usize)) -> Self {
+        let board = Self::compose(frame, mode);
+        let hud = hud_lines(frame.hud(), board.cols);
+        let (width, height) = Self::fit_size(frame, mode);
+        let left = cols.saturating_sub(width) / 2;
+        let mut top = rows.saturating_sub(height) / 2;
+
+        let mut screen = Self {
+            cols,
+            rows,
+            cells: vec![
+                Cell {
+                    c: ' ',
+                    fg: FOREGROUND,
+                    bg: OUTSIDE,
+                };
+                cols * rows
+            ],
+        };
+        for line in hud {
+            for (i, c) in line.chars().enumerate() {
+                screen.set(
+                    left + 1 + i,
+                    top,
+                    Cell {
+                        c,
+                        fg: FOREGROUND,
+                        bg: OUTSIDE,
+                    },
+                );
+            }
+            top += 1;
+        }
+
+        let right = left + board.cols + 1;
+        let bottom = top + board.rows + 1;
+        for x in left + 1..right {
+            screen.set(x, top, Cell::new('─'));
+            screen.set(x, bottom, Cell::new('─'));
+        }
+        for y in top + 1..bottom {
+            screen.set(left, y, Cell::new('│'));
+            screen.set(right, y, Cell::new('│'));
+        }
+        screen.set(left, top, Cell::new('┌'));
+        screen.set(right, top, Cell::new('┐'));
+        screen.set(left, bottom, Cell::new('└'));
+        screen.set(right, bottom, Cell::new('┘'));
+
+        for y in 0..board.rows {
+            for x in 0..board.cols {
+                screen.set(left + 1 + x, top + 1 + y, board.get(x, y));
+            }
+        }
+        for (y, line) in frame.overlay().iter().take(board.rows).enumerate() {
+            for (x, c) in line.chars().take(board.cols).enumerate() {
+                let cell = Cell {
+                    c,
+                    fg: FOREGROUND,
+                    bg: OUTSIDE,
+                };
+                screen.set(left + 1 + x, top + 1 + y, cell);
+            }
+        }
+        screen
+    }
+
+    /// Smallest terminal where the whole layout is visible.
+    pub fn fit_size(frame: &Frame, mode: RenderMode) -> (usize, usize) {
+        let width = frame.cols() * mode.cell_width();
+        let hud = hud_lines(frame.hud(), width).len();
+        (
+            width + 2,
+            frame.rows().div_ceil(mode.cell_height()) + 2 + hud,
+        )
+    }
+
+    /// Board alone, without box and HUD.
+    pub fn compose(frame: &Frame, mode: RenderMode) -> Self {
+        let width = mode.cell_width();
+        let height = mode.cell_height();
+        let rows = frame.rows().div_ceil(height);
+        let mut screen = Self::new(frame.cols() * width, rows);
+        for x in 0..frame.cols() {
+            for y in 0..rows {
+                match mode {
+                    RenderMode::Normal => screen.set(x, y, Cell::new(frame[x][y])),
+                    RenderMode::DoubleWidth => {
+                        let [left, right] = glyph_pair(frame[x][y]);
+                        screen.set(x * 2, y, Cell::new(left));
+                        screen.set(x * 2 + 1, y, Cell::new(right));
+                    }
+                    RenderMode::HalfBlock => {
+                        let top = frame[x][y * 2];
+                        let bottom = frame[x].get(y * 2 + 1).copied().unwrap_or(' ');
+                        screen.set(x, y, half_block(top, bottom));
+                    }
+                }
+            }
+        }
+        for (start, text) in frame.texts() {
+            for (i, c) in text.chars().enumerate() {
+                screen.set(start.x * width + i, start.y / height, Cell::new(c));
+            }
+        }
+        screen
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.cols + x]
+    }
+
+    /// Cells outside of the screen are dropped.
+    fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.cols && y < self.rows {
+            self.cells[y * self.cols + x] = cell;
+        }
+    }
+
+    /// Characters of one row, handy in tests.
+    pub fn line(&self, y: usize) -> String {
+        self.cells[y * self.cols..(y + 1) * self.cols]
+            .iter()
+            .map(|cell| cell.c)
+            .collect()
+    }
+}
+
+/// HUD items are put one after another while they fit in the width of
+/// the board, then the next line is started. Too long item is cut.
+fn hud_lines(items: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for item in items {
+        let item: String = item.chars().take(width).collect();
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + HUD_GAP + item.chars().count() <= width => {
+                line.push_str(&" ".repeat(HUD_GAP));
+                line.push_str(&item);
+            }
+            _ => lines.push(item),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::{draw_hud, draw_overlay, draw_text, new_frame},
+        Point, NUM_COLS, NUM_ROWS,
+    };
+
+    #[test]
+    fn test_compose() {
+        let mut frame = new_frame();
+        frame[1][1] = 'X';
+        frame[2][1] = '⇒';
+        draw_text(&mut frame, Point::new(4, 1), "Score");
+        draw_text(&mut frame, Point::new(NUM_COLS - 2, 2), "cut off");
+
+        let normal = Screen::compose(&frame, RenderMode::Normal);
+        assert_eq!(normal.cols(), NUM_COLS);
+        assert_eq!(normal.line(1).trim_end(), " X⇒ Score");
+        assert_eq!(normal.line(2).trim(), "cu");
+
+        let double = Screen::compose(&frame, RenderMode::DoubleWidth);
+        assert_eq!(double.cols(), NUM_COLS * 2);
+        assert_eq!(
+            double.line(1).trim_end(),
+            "  []=>  Score",
+            "Cells are doubled, text is not."
+        );
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let mut frame = new_frame();
+        frame[0][2] = 'X';
+        frame[1][3] = 'Q';
+        frame[2][2] = 'X';
+        frame[2][3] = 'X';
+        frame[3][2] = '⇑';
+        frame[3][3] = 'X';
+
+        let screen = Screen::compose(&frame, RenderMode::HalfBlock);
+        assert_eq!(screen.rows(), NUM_ROWS / 2);
+        assert_eq!(screen.line(1).trim_end(), "▀▄█▀");
+        assert_eq!(screen.get(1, 1).fg, Color::Red);
+        assert_eq!(
+            (screen.get(3, 1).fg, screen.get(3, 1).bg),
+            (Color::Green, Color::DarkGreen),
+            "Top one is foreground, bottom one is background."
+        );
+        assert_eq!(screen.get(0, 0), Cell::default());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut frame = new_frame();
+        frame[0][0] = 'X';
+        draw_hud(&mut frame, "Score");
+        let (cols, rows) = Screen::fit_size(&frame, RenderMode::Normal);
+        assert_eq!((cols, rows), (NUM_COLS + 2, NUM_ROWS + 3));
+
+        let screen = Screen::layout(&frame, RenderMode::Normal, (cols + 10, rows + 4));
+        assert_eq!(screen.line(2).trim_end(), "      Score");
+        assert!(screen.line(3).starts_with("     ┌──"));
+        assert!(screen.line(4).starts_with("     │X "));
+        assert!(screen.line(rows + 1).starts_with("     └──"));
+        assert_eq!(screen.get(0, 0).bg, OUTSIDE);
+        assert_eq!(
+            screen.get(6, 4).bg,
+            BACKGROUND,
+            "Board keeps its background."
+        );
+
+        let mut with_overlay = frame.clone();
+        draw_overlay(&mut with_overlay, "FPS: 60");
+        assert_eq!(
+            Screen::fit_size(&with_overlay, RenderMode::Normal),
+            (cols, rows),
+            "Overlay doesn't change the layout."
+        );
+        let covered = Screen::layout(&with_overlay, RenderMode::Normal, (cols + 10, rows + 4));
+        assert!(covered.line(4).contains("│FPS: 60"));
+        assert_eq!(covered.get(6, 4).bg, OUTSIDE);
+
+        let small = Screen::layout(&frame, RenderMode::Normal, (10, 5));
+        assert_eq!(
+            small.line(1),
+            "┌─────────",
+            "Too small terminal cuts the board."
+        );
+    }
+
+    #[test]
+    fn test_hud_lines() {
+        let items = ["Score: 7", "Time: 10", "Length: 4", "Seed: 123456789"].map(String::from);
+        assert_eq!(
+            hud_lines(&items, 20),
+            ["Score: 7  Time: 10", "Length: 4", "Seed: 123456789"],
+            "Items go to next line when they don't fit."
+        );
+        assert_eq!(hud_lines(&items, 40).len(), 2);
+        assert_eq!(hud_lines(&items[3..], 6), ["Seed: "]);
+    }
+}
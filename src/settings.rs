@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hud::HudWidget,
+    screen::RenderMode,
+    snake::{Controls, TURN_BUFFER},
+    storage,
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+pub const MAX_VOLUME: u8 = 10;
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Player preferences which survive restart of the game. Missing fields
+/// take default values, so old settings file still loads after update.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: u8,
+    pub sfx_volume: u8,
+    pub music_volume: u8,
+    pub muted: bool,
+    /// Directory with sounds replacing built-in ones.
+    pub audio_dir: Option<PathBuf>,
+    /// Sound pack directory with `pack.json` manifest.
+    pub sound_pack: Option<PathBuf>,
+    /// Name under which lifetime stats are collected.
+    pub profile: String,
+    /// Two terminal columns for one board cell, so the board looks square.
+    pub render_mode: RenderMode,
+    /// Keep game interrupted with `Esc` to continue it later.
+    pub save_on_quit: bool,
+    /// Information shown above the board.
+    pub hud: Vec<HudWidget>,
+    /// Turns pressed quickly one after another wait for the next moves,
+    /// this many at most.
+    pub turn_buffer: usize,
+    pub controls: Controls,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: MAX_VOLUME,
+            sfx_volume: MAX_VOLUME,
+            music_volume: 7,
+            muted: false,
+            audio_dir: None,
+            sound_pack: None,
+            profile: String::from(DEFAULT_PROFILE),
+            render_mode: RenderMode::Normal,
+            save_on_quit: true,
+            hud: HudWidget::DEFAULT.to_vec(),
+            turn_buffer: TURN_BUFFER,
+            controls: Controls::Arrows,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        storage::config_file(SETTINGS_FILE)
+    }
+
+    pub fn load() -> Self {
+        match Self::path() {
+            Some(path) => Self::load_from(path),
+            None => Self::default(),
+        }
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Self {
+        if !path.as_ref().exists() {
+            return Self::default();
+        }
+        match storage::load_json(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Can't read settings {}: {}", path.as_ref().display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Final volume of sound effects, with master volume and mute applied.
+    pub fn sfx_level(&self) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        volume_level(self.master_volume) * volume_level(self.sfx_volume)
+    }
+
+    pub fn music_level(&self) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        volume_level(self.master_volume) * volume_level(self.music_volume)
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = Self::path() {
+            self.save_to(path);
+        }
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) {
+        match storage::save_json(&path, self) {
+            Ok(()) => info!("Settings saved to {}", path.as_ref().display()),
+            Err(e) => error!("Can't save settings {}: {}", path.as_ref().display(), e),
+        }
+    }
+}
+
+/// Converts volume step from menu into value for audio sinks.
+pub fn volume_level(step: u8) -> f32 {
+    step.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir().join(format!("snake_settings_{}.json", std::process::id()));
+        let settings = Settings {
+            master_volume: 3,
+            sfx_volume: 5,
+            music_volume: 0,
+            muted: true,
+            audio_dir: Some(PathBuf::from("my_sounds")),
+            sound_pack: None,
+            profile: String::from("tester"),
+            render_mode: RenderMode::DoubleWidth,
+            save_on_quit: false,
+            hud: vec![HudWidget::Seed],
+            turn_buffer: 4,
+            controls: Controls::Relative,
+        };
+        settings.save_to(&path);
+        assert_eq!(Settings::load_from(&path), settings);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Settings::load_from(&path),
+            Settings::default(),
+            "Defaults when there is no file."
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_are_default() {
+        let settings: Settings = serde_json::from_str(r#"{"muted": true}"#).unwrap();
+        assert!(settings.muted);
+        assert_eq!(settings.master_volume, Settings::default().master_volume);
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut settings = Settings {
+            master_volume: 5,
+            sfx_volume: MAX_VOLUME,
+            music_volume: 0,
+            muted: false,
+            audio_dir: None,
+            sound_pack: None,
+            profile: String::from(DEFAULT_PROFILE),
+            render_mode: RenderMode::Normal,
+            save_on_quit: true,
+            hud: Vec::new(),
+            turn_buffer: 1,
+            controls: Controls::Arrows,
+        };
+        assert_eq!(settings.sfx_level(), 0.5);
+        assert_eq!(settings.music_level(), 0.0);
+        settings.muted = true;
+        assert_eq!(settings.sfx_level(), 0.0, "Mute silences everything.");
+    }
+
+    #[test]
+    fn test_volume_level() {
+        assert_eq!(volume_level(0), 0.0);
+        assert_eq!(volume_level(MAX_VOLUME), 1.0);
+        assert_eq!(volume_level(MAX_VOLUME + 5), 1.0);
+    }
+}
@@ -1,22 +1,72 @@
-use std::time::Duration;
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     apple::AppleDispencer,
+    events::{DeathCause, EventQueue, GameEvent},
     frame::{Drawable, Frame},
+    level::Level,
     timer::Timer,
-    Point, NUM_COLS, NUM_ROWS,
+    Point,
 };
 
+/// Turns pressed ahead of the snake, kept until it moves.
+pub const TURN_BUFFER: usize = 2;
+pub const TURN_BUFFER_LIMITS: (usize, usize) = (1, 5);
+
+fn default_turn_buffer() -> usize {
+    TURN_BUFFER
+}
+
+/// How keys steer the snake: arrows point where to go, or with
+/// relative controls left and right turn it from where it goes now.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Controls {
+    #[default]
+    Arrows,
+    Relative,
+}
+
+impl Controls {
+    pub const ALL: [Controls; 2] = [Controls::Arrows, Controls::Relative];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Controls::Arrows => "arrows",
+            Controls::Relative => "relative",
+        }
+    }
+}
+
+/// Turn from the snake's point of view.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct Snake {
     body: Vec<Point>,
     direction: Direction,
     timer: Timer,
     alive: bool,
-    can_turn: bool,
+    /// Turns waiting for the next moves, one is taken a move.
+    #[serde(default)]
+    turns: VecDeque<Direction>,
+    #[serde(default = "default_turn_buffer")]
+    turn_buffer: usize,
     adding_tail: bool,
+    death_cause: Option<DeathCause>,
+    /// Board the snake moves on, saved games from before levels get
+    /// the default one.
+    #[serde(default)]
+    level: Level,
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Direction {
     Left,
     Up,
@@ -24,34 +74,101 @@ pub enum Direction {
     Down,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+    ];
+
+    /// Lowercase name, as bots and other programs see it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Left => "left",
+            Direction::Up => "up",
+            Direction::Right => "right",
+            Direction::Down => "down",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Where a snake going this way goes after the turn.
+    pub fn turned(self, turn: Turn) -> Direction {
+        let index = Self::ALL.iter().position(|d| *d == self).unwrap_or(0);
+        // ALL goes clockwise
+        let next = match turn {
+            Turn::Left => index + 3,
+            Turn::Right => index + 1,
+        };
+        Self::ALL[next % 4]
+    }
+
+    /// Snake can only turn sideways, not back and not the way it goes.
+    fn can_turn_to(self, to: Direction) -> bool {
+        match self {
+            Direction::Left | Direction::Right => matches!(to, Direction::Up | Direction::Down),
+            Direction::Up | Direction::Down => matches!(to, Direction::Left | Direction::Right),
+        }
+    }
+}
+
 impl Snake {
     pub fn new(speed: u64) -> Self {
-        let head = Point::new(20, 20);
-        let body = vec![
-            head,
-            Point::new(head.x - 1, head.y),
-            Point::new(head.x - 2, head.y),
-            Point::new(head.x - 3, head.y),
-        ];
+        Self::on_level(Level::default(), speed)
+    }
+    pub fn on_level(level: Level, speed: u64) -> Self {
         Self {
-            body,
+            body: level.start_body(),
             direction: Direction::Right,
             timer: Timer::new(Duration::from_millis(speed)),
             alive: true,
-            can_turn: true,
+            turns: VecDeque::new(),
+            turn_buffer: TURN_BUFFER,
             adding_tail: false,
+            death_cause: None,
+            level,
         }
     }
-    pub fn update<F>(&mut self, delta: Duration, mut do_if_move: F)
-    where
-        F: FnMut(),
-    {
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+    pub fn speed(&self) -> u64 {
+        self.timer.max().as_millis() as u64
+    }
+    /// How many turns can wait for the next moves, at least one.
+    pub fn set_turn_buffer(&mut self, turn_buffer: usize) {
+        self.turn_buffer = turn_buffer.max(1);
+        self.turns.truncate(self.turn_buffer);
+    }
+    /// Time until the next move.
+    pub fn time_to_move(&self) -> Duration {
+        self.timer.remaining()
+    }
+    pub fn update(&mut self, delta: Duration, events: &mut EventQueue) {
+        if self.is_dead() {
+            return;
+        }
         self.timer.tick(delta);
         if self.timer.finished() {
             self.timer.reset();
+            // Checked again, the direction may be other than when pressed
+            if let Some(turn) = self.turns.pop_front() {
+                if self.direction.can_turn_to(turn) {
+                    self.direction = turn;
+                    events.push(GameEvent::Turned { direction: turn });
+                }
+            }
             self.next_move();
-            do_if_move();
-            self.can_turn = true;
+            match self.death_cause {
+                Some(cause) => events.push(GameEvent::Died { cause }),
+                None => events.push(GameEvent::Moved { head: self.body[0] }),
+            }
         }
     }
     fn next_move(&mut self) {
@@ -62,7 +179,7 @@ impl Snake {
         }
 
         self.body.insert(0, new_head_position);
-        if self.adding_tail == true {
+        if self.adding_tail {
             self.adding_tail = false;
         } else {
             self.body.pop();
@@ -70,7 +187,7 @@ impl Snake {
     }
     fn move_or_dead(&mut self, next_section: &mut Point) {
         if self.is_boarder_next(next_section) {
-            self.alive = false;
+            self.die(DeathCause::Wall);
             return;
         }
         match self.direction {
@@ -79,18 +196,22 @@ impl Snake {
             Direction::Right => next_section.x += 1,
             Direction::Down => next_section.y += 1,
         };
-        if self.is_tail_next(next_section) {
-            self.alive = false;
-            return;
+        if self.level.is_wall(*next_section) {
+            self.die(DeathCause::Wall);
+        } else if self.is_tail_next(next_section) {
+            self.die(DeathCause::Tail);
         }
     }
+    fn die(&mut self, cause: DeathCause) {
+        self.alive = false;
+        self.death_cause = Some(cause);
+    }
     fn is_boarder_next(&self, next_section: &mut Point) -> bool {
         match (self.direction, next_section) {
             (Direction::Left, Point { x: 0, .. }) => true,
-            //Note: start from 1 to avoid topbar, temporary solution for now
-            (Direction::Up, Point { y: 1, .. }) => true,
-            (Direction::Right, Point { x, .. }) if *x == NUM_COLS - 1 => true,
-            (Direction::Down, Point { y, .. }) if *y == NUM_ROWS - 1 => true,
+            (Direction::Up, Point { y: 0, .. }) => true,
+            (Direction::Right, Point { x, .. }) if *x == self.level.size.cols - 1 => true,
+            (Direction::Down, Point { y, .. }) if *y == self.level.size.rows - 1 => true,
             _ => false,
         }
     }
@@ -105,33 +226,44 @@ impl Snake {
     pub fn is_dead(&self) -> bool {
         !self.alive
     }
-    //Turn left or rigth relative to current direction, plus
-    //can turn only once a move
-    pub fn turn_if_possible(&mut self, new_dirrection: Direction) {
-        if self.can_turn == false {
-            return;
+    pub fn death_cause(&self) -> Option<DeathCause> {
+        self.death_cause
+    }
+    pub fn head(&self) -> Point {
+        self.body[0]
+    }
+    /// Head first.
+    pub fn body(&self) -> &[Point] {
+        &self.body
+    }
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+    /// Where the snake goes after the turns which wait, relative turns
+    /// are made from here.
+    pub fn heading(&self) -> Direction {
+        self.turns.back().copied().unwrap_or(self.direction)
+    }
+    /// Turn for one of the next moves, taken one a move in order pressed.
+    /// Turn back or the way the snake will already go is dropped, so is
+    /// a turn when the buffer is full.
+    pub fn queue_turn(&mut self, direction: Direction) {
+        if self.turns.len() < self.turn_buffer && self.heading().can_turn_to(direction) {
+            self.turns.push_back(direction);
         }
-        self.direction = match (self.direction, new_dirrection) {
-            (Direction::Left, x) if x == Direction::Up || x == Direction::Down => new_dirrection,
-            (Direction::Up, x) if x == Direction::Left || x == Direction::Right => new_dirrection,
-            (Direction::Right, x) if x == Direction::Up || x == Direction::Down => new_dirrection,
-            (Direction::Down, x) if x == Direction::Left || x == Direction::Right => new_dirrection,
-            (_, _) => return,
-        };
-        self.can_turn = false;
     }
-    pub fn check_if_ate_apple<W>(&mut self, dispencer: &mut AppleDispencer, mut do_if_ate: W)
-    where
-        W: FnMut(),
-    {
+    pub fn check_if_ate_apple(&mut self, dispencer: &mut AppleDispencer, events: &mut EventQueue) {
         for snake_part in &self.body {
-            dispencer.eat_apples_if(|p| {
+            dispencer.eat_apples_if(|p, kind| {
                 if p == snake_part {
                     self.adding_tail = true;
-                    do_if_ate();
+                    events.push(GameEvent::AteApple { kind, pos: *p });
                     return true;
                 }
-                return false;
+                false
             });
         }
     }
@@ -155,3 +287,146 @@ impl Drawable for Snake {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+
+    const SPEED: u64 = 100;
+
+    fn step(snake: &mut Snake, events: &mut EventQueue) {
+        snake.update(Duration::from_millis(SPEED), events);
+    }
+
+    #[test]
+    fn test_move_events() {
+        let mut snake = Snake::new(SPEED);
+        let mut events = EventQueue::new();
+        snake.update(Duration::from_millis(SPEED / 2), &mut events);
+        assert!(events.is_empty(), "No move before timer finished.");
+        snake.update(Duration::from_millis(SPEED / 2), &mut events);
+        assert_eq!(
+            events.events(),
+            &[GameEvent::Moved {
+                head: Point::new(21, 20)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_turn_once_per_move() {
+        let mut snake = Snake::new(SPEED);
+        let mut events = EventQueue::new();
+        snake.queue_turn(Direction::Left);
+        snake.queue_turn(Direction::Right);
+        step(&mut snake, &mut events);
+        assert_eq!(snake.head(), Point::new(21, 20), "Can't turn back.");
+        snake.queue_turn(Direction::Up);
+        snake.queue_turn(Direction::Left);
+        events = EventQueue::new();
+        step(&mut snake, &mut events);
+        assert_eq!(
+            events.events(),
+            &[
+                GameEvent::Turned {
+                    direction: Direction::Up
+                },
+                GameEvent::Moved {
+                    head: Point::new(21, 19)
+                }
+            ],
+            "One turn a move."
+        );
+        step(&mut snake, &mut events);
+        assert_eq!(
+            snake.head(),
+            Point::new(20, 19),
+            "Second turn on next move."
+        );
+    }
+
+    #[test]
+    fn test_relative_turns() {
+        assert_eq!(Direction::Up.turned(Turn::Left), Direction::Left);
+        assert_eq!(Direction::Left.turned(Turn::Left), Direction::Down);
+        assert_eq!(Direction::Left.turned(Turn::Right), Direction::Up);
+        assert_eq!(Direction::Down.turned(Turn::Right), Direction::Left);
+
+        let mut snake = Snake::new(SPEED);
+        let mut events = EventQueue::new();
+        for _ in 0..2 {
+            let direction = snake.heading().turned(Turn::Left);
+            snake.queue_turn(direction);
+        }
+        assert_eq!(
+            snake.heading(),
+            Direction::Left,
+            "Turns from the last waiting one."
+        );
+        step(&mut snake, &mut events);
+        step(&mut snake, &mut events);
+        assert_eq!(snake.head(), Point::new(19, 19));
+    }
+
+    #[test]
+    fn test_turn_buffer() {
+        let mut snake = Snake::new(SPEED);
+        let mut events = EventQueue::new();
+        snake.set_turn_buffer(1);
+        snake.queue_turn(Direction::Up);
+        snake.queue_turn(Direction::Left);
+        step(&mut snake, &mut events);
+        step(&mut snake, &mut events);
+        assert_eq!(snake.head(), Point::new(20, 18), "Buffer was full.");
+
+        snake.set_turn_buffer(3);
+        snake.queue_turn(Direction::Up);
+        snake.queue_turn(Direction::Right);
+        snake.queue_turn(Direction::Up);
+        snake.queue_turn(Direction::Right);
+        snake.queue_turn(Direction::Up);
+        for _ in 0..4 {
+            step(&mut snake, &mut events);
+        }
+        assert_eq!(
+            snake.head(),
+            Point::new(23, 17),
+            "First Up is the way it goes, last Up didn't fit."
+        );
+        assert_eq!(snake.direction(), Direction::Right);
+    }
+
+    #[test]
+    fn test_wall_death() {
+        let mut snake = Snake::new(SPEED);
+        let mut events = EventQueue::new();
+        while !snake.is_dead() {
+            step(&mut snake, &mut events);
+        }
+        assert_eq!(snake.head(), Point::new(crate::NUM_COLS - 1, 20));
+        assert_eq!(snake.death_cause(), Some(DeathCause::Wall));
+        assert_eq!(
+            events.events().last(),
+            Some(&GameEvent::Died {
+                cause: DeathCause::Wall
+            })
+        );
+        let count = events.events().len();
+        step(&mut snake, &mut events);
+        assert_eq!(events.events().len(), count, "Dead snake doesn't move.");
+    }
+
+    #[test]
+    fn test_level_walls() {
+        let mut level = Level::empty(Size::new(12, 10));
+        level.toggle_wall(Point::new(10, 6));
+        let mut snake = Snake::on_level(level, SPEED);
+        let mut events = EventQueue::new();
+        assert_eq!(snake.head(), Point::new(8, 6));
+        step(&mut snake, &mut events);
+        step(&mut snake, &mut events);
+        assert_eq!(snake.head(), Point::new(9, 6), "Stopped before the wall.");
+        assert_eq!(snake.death_cause(), Some(DeathCause::Wall));
+    }
+}
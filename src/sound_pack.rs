@@ -0,0 +1,234 @@
+//! Sound pack is a directory with sound files and `pack.json` manifest,
+//! which says what to play for each game event. Event can have several
+//! sounds, picked randomly or one after another, each with own volume.
+//! Eating can be set per apple kind (`eat.regular`), otherwise `eat` is used.
+//! Default pack is built in, see `audio/pack.json`.
+
+use std::{collections::HashMap, io, path::Path};
+
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{apple::AppleKind, mixer::Priority, storage};
+
+pub const MANIFEST_FILE: &str = "pack.json";
+const BUILTIN_MANIFEST: &str = include_str!("../audio/pack.json");
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundEvent {
+    MenuNavigate,
+    Start,
+    Move,
+    Eat(AppleKind),
+    Die,
+    NewHighScore,
+}
+
+impl SoundEvent {
+    /// Keys in manifest, the first one found is used.
+    fn keys(&self) -> Vec<String> {
+        match self {
+            SoundEvent::MenuNavigate => vec![String::from("menu_navigate")],
+            SoundEvent::Start => vec![String::from("start")],
+            SoundEvent::Move => vec![String::from("move")],
+            SoundEvent::Eat(kind) => vec![format!("eat.{}", kind.name()), String::from("eat")],
+            SoundEvent::Die => vec![String::from("die")],
+            SoundEvent::NewHighScore => vec![String::from("new_high_score")],
+        }
+    }
+
+    pub fn priority(&self) -> Priority {
+        match self {
+            SoundEvent::MenuNavigate | SoundEvent::Move => Priority::Low,
+            SoundEvent::Start | SoundEvent::Eat(_) | SoundEvent::NewHighScore => Priority::Normal,
+            SoundEvent::Die => Priority::High,
+        }
+    }
+
+    /// Music gets quieter under these sounds.
+    pub fn ducks_music(&self) -> bool {
+        *self == SoundEvent::Die
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    #[default]
+    Random,
+    RoundRobin,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+enum SoundRef {
+    Name(String),
+    WithVolume {
+        file: String,
+        #[serde(default = "full_volume")]
+        volume: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct EventSounds {
+    sounds: Vec<SoundRef>,
+    #[serde(default)]
+    selection: Selection,
+    #[serde(default = "full_volume")]
+    volume: f32,
+}
+
+fn full_volume() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct Manifest {
+    #[serde(default)]
+    name: String,
+    events: HashMap<String, EventSounds>,
+}
+
+pub struct SoundPack {
+    manifest: Manifest,
+    next: HashMap<String, usize>,
+}
+
+impl SoundPack {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_MANIFEST).expect("Built-in sound pack manifest is valid.")
+    }
+
+    /// Reads manifest from pack directory, sound files are loaded by `Audio`.
+    pub fn load<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let manifest = storage::load_json(dir.as_ref().join(MANIFEST_FILE))?;
+        Ok(Self::from_manifest(manifest))
+    }
+
+    pub fn parse(manifest: &str) -> io::Result<Self> {
+        let manifest = serde_json::from_str(manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::from_manifest(manifest))
+    }
+
+    fn from_manifest(manifest: Manifest) -> Self {
+        Self {
+            manifest,
+            next: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    /// Chooses track name and its volume for the event.
+    pub fn pick(&mut self, event: SoundEvent) -> Option<(String, f32)> {
+        let (key, sounds) = event
+            .keys()
+            .into_iter()
+            .find_map(|key| self.manifest.events.get(&key).map(|sounds| (key, sounds)))?;
+        if sounds.sounds.is_empty() {
+            return None;
+        }
+        let index = match sounds.selection {
+            Selection::Random => thread_rng().gen_range(0..sounds.sounds.len()),
+            Selection::RoundRobin => {
+                let next = self.next.entry(key).or_insert(0);
+                let index = *next % sounds.sounds.len();
+                *next = index + 1;
+                index
+            }
+        };
+        let (file, volume) = match &sounds.sounds[index] {
+            SoundRef::Name(file) => (file, 1.0),
+            SoundRef::WithVolume { file, volume } => (file, *volume),
+        };
+        Some((track_name(file), volume * sounds.volume))
+    }
+}
+
+/// Manifest may use file names, tracks are known by names without extension.
+fn track_name(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "name": "test",
+        "events": {
+            "move": { "sounds": ["step.wav", { "file": "step2.ogg", "volume": 0.5 }],
+                      "selection": "round_robin", "volume": 0.8 },
+            "eat": { "sounds": ["crunch"] },
+            "die": { "sounds": [] }
+        }
+    }"#;
+
+    #[test]
+    fn test_builtin_pack() {
+        let mut pack = SoundPack::builtin();
+        assert_eq!(pack.name(), "default");
+        for event in [
+            SoundEvent::MenuNavigate,
+            SoundEvent::Start,
+            SoundEvent::Move,
+            SoundEvent::Eat(AppleKind::Regular),
+            SoundEvent::Die,
+            SoundEvent::NewHighScore,
+        ] {
+            assert!(pack.pick(event).is_some(), "Every event has sound.");
+        }
+    }
+
+    #[test]
+    fn test_round_robin_and_volume() {
+        let mut pack = SoundPack::parse(MANIFEST).unwrap();
+        assert_eq!(
+            pack.pick(SoundEvent::Move),
+            Some((String::from("step"), 0.8))
+        );
+        assert_eq!(
+            pack.pick(SoundEvent::Move),
+            Some((String::from("step2"), 0.4))
+        );
+        assert_eq!(
+            pack.pick(SoundEvent::Move),
+            Some((String::from("step"), 0.8))
+        );
+    }
+
+    #[test]
+    fn test_eat_by_kind() {
+        let mut pack = SoundPack::parse(MANIFEST).unwrap();
+        assert_eq!(
+            pack.pick(SoundEvent::Eat(AppleKind::Regular)).unwrap().0,
+            "crunch",
+            "Falls back to common eat sound."
+        );
+        let mut pack = SoundPack::parse(
+            r#"{ "events": { "eat": { "sounds": ["crunch"] },
+                             "eat.regular": { "sounds": ["bling"] } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pack.pick(SoundEvent::Eat(AppleKind::Regular)).unwrap().0,
+            "bling"
+        );
+    }
+
+    #[test]
+    fn test_missing_sounds() {
+        let mut pack = SoundPack::parse(MANIFEST).unwrap();
+        assert_eq!(pack.pick(SoundEvent::Die), None);
+        assert_eq!(pack.pick(SoundEvent::NewHighScore), None);
+        assert!(SoundPack::parse("{ not json").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Lifetime statistics are kept for every profile separately in
+//! `stats.json`. Each finished game is added to totals of the current
+//! profile, games saved on quit are counted only when they are finally
+//! over.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::DeathCause,
+    frame::{draw_text, Drawable},
+    storage, Point, NUM_COLS, NUM_ROWS,
+};
+
+const STATS_FILE: &str = "stats.json";
+const MAX_SCORES: usize = 10;
+
+/// What is known about a game when it is over.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameResult {
+    pub score: usize,
+    pub time: usize,
+    pub apples: usize,
+    pub length: usize,
+    pub speed: u64,
+    /// `None` when game was left before the snake died.
+    pub death: Option<DeathCause>,
+}
+
+/// One of the best games of a profile.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Score {
+    pub score: usize,
+    pub time: usize,
+    pub speed: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct SpeedStats {
+    pub games: u32,
+    pub total_score: u64,
+}
+
+impl SpeedStats {
+    pub fn average_score(&self) -> f32 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        self.total_score as f32 / self.games as f32
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub apples_eaten: u64,
+    /// Seconds in all games together.
+    pub play_time: u64,
+    pub longest_snake: usize,
+    pub best_time: usize,
+    pub wall_deaths: u32,
+    pub tail_deaths: u32,
+    /// Keyed by snake speed in milliseconds per move.
+    pub speeds: BTreeMap<u64, SpeedStats>,
+    /// Best games sorted from the highest, only top ones are kept.
+    pub scores: Vec<Score>,
+}
+
+impl Stats {
+    pub fn record(&mut self, game: &GameResult) {
+        self.games_played += 1;
+        self.apples_eaten += game.apples as u64;
+        self.play_time += game.time as u64;
+        self.longest_snake = self.longest_snake.max(game.length);
+        self.best_time = self.best_time.max(game.time);
+        match game.death {
+            Some(DeathCause::Wall) => self.wall_deaths += 1,
+            Some(DeathCause::Tail) => self.tail_deaths += 1,
+            None => {}
+        }
+        let speed = self.speeds.entry(game.speed).or_default();
+        speed.games += 1;
+        speed.total_score += game.score as u64;
+        self.add_score(Score {
+            score: game.score,
+            time: game.time,
+            speed: game.speed,
+        });
+    }
+
+    pub fn best_score(&self) -> usize {
+        self.scores.first().map_or(0, |entry| entry.score)
+    }
+
+    /// Puts result into the best scores, empty games don't get there.
+    fn add_score(&mut self, score: Score) {
+        if score.score == 0 {
+            return;
+        }
+        let place = self
+            .scores
+            .iter()
+            .position(|entry| entry.score < score.score)
+            .unwrap_or(self.scores.len());
+        if place < MAX_SCORES {
+            self.scores.insert(place, score);
+            self.scores.truncate(MAX_SCORES);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct StatsBook {
+    profiles: BTreeMap<String, Stats>,
+}
+
+impl StatsBook {
+    pub fn path() -> Option<PathBuf> {
+        storage::config_file(STATS_FILE)
+    }
+
+    pub fn load() -> Self {
+        match Self::path() {
+            Some(path) => Self::load_from(path),
+            None => Self::default(),
+        }
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Self {
+        if !path.as_ref().exists() {
+            return Self::default();
+        }
+        storage::load_json(&path).unwrap_or_else(|e| {
+            error!("Can't read stats {}: {}", path.as_ref().display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = Self::path() {
+            self.save_to(path);
+        }
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) {
+        if let Err(e) = storage::save_json(&path, self) {
+            error!("Can't save stats {}: {}", path.as_ref().display(), e);
+        }
+    }
+
+    /// Empty stats for profile which has not played yet.
+    pub fn profile(&self, name: &str) -> Stats {
+        self.profiles.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn record(&mut self, profile: &str, game: &GameResult) {
+        self.profiles
+            .entry(String::from(profile))
+            .or_default()
+            .record(game);
+    }
+}
+
+/// Stats screen, opened from the menu.
+pub struct StatsView<'a> {
+    pub profile: &'a str,
+    pub stats: &'a Stats,
+}
+
+fn format_time(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+impl StatsView<'_> {
+    /// Text of the screen, also printed by `snake stats`.
+    pub fn lines(&self) -> Vec<String> {
+        let stats = self.stats;
+        let mut lines = vec![
+            format!("Profile: {}", self.profile),
+            String::new(),
+            format!("Games played: {:>9}", stats.games_played),
+            format!("Apples eaten: {:>9}", stats.apples_eaten),
+            format!("Play time:    {:>9}", format_time(stats.play_time)),
+            format!("Longest snake:{:>9}", stats.longest_snake),
+            format!("Best time:    {:>9}", format_time(stats.best_time as u64)),
+            format!("Wall deaths:  {:>9}", stats.wall_deaths),
+            format!("Tail deaths:  {:>9}", stats.tail_deaths),
+            String::new(),
+            String::from("Average score by speed:"),
+        ];
+        for (speed, speed_stats) in &stats.speeds {
+            lines.push(format!(
+                "{:>5}ms {:>6.1} ({})",
+                speed,
+                speed_stats.average_score(),
+                speed_stats.games
+            ));
+        }
+        lines
+    }
+}
+
+impl Drawable for StatsView<'_> {
+    fn draw(&self, frame: &mut crate::frame::Frame) {
+        let mut lines = self.lines();
+        // Leave room for the hint at the bottom.
+        lines.truncate(NUM_ROWS - 5);
+        for (i, line) in lines.iter().enumerate() {
+            let line: String = line.chars().take(NUM_COLS - 2).collect();
+            draw_text(frame, Point::new(2, 1 + i), &line);
+        }
+        draw_text(
+            frame,
+            Point::new(2, NUM_ROWS - 2),
+            "Press any key to go back",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{
+        frame::new_frame,
+        screen::{RenderMode, Screen},
+    };
+
+    fn game(score: usize, speed: u64, death: Option<DeathCause>) -> GameResult {
+        GameResult {
+            score,
+            time: score * 10,
+            apples: score,
+            length: 4 + score,
+            speed,
+            death,
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut stats = Stats::default();
+        stats.record(&game(4, 300, Some(DeathCause::Wall)));
+        stats.record(&game(2, 300, Some(DeathCause::Tail)));
+        stats.record(&game(9, 150, Some(DeathCause::Wall)));
+        stats.record(&game(1, 150, None));
+        assert_eq!(stats.games_played, 4);
+        assert_eq!(stats.apples_eaten, 16);
+        assert_eq!(stats.play_time, 160);
+        assert_eq!(stats.longest_snake, 13);
+        assert_eq!(stats.best_time, 90);
+        assert_eq!((stats.wall_deaths, stats.tail_deaths), (2, 1));
+        assert_eq!(stats.speeds[&300].average_score(), 3.0);
+        assert_eq!(stats.speeds[&150].average_score(), 5.0);
+    }
+
+    #[test]
+    fn test_best_scores() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.best_score(), 0);
+        stats.record(&game(0, 300, None));
+        assert!(stats.scores.is_empty(), "Empty game is not a record.");
+        stats.record(&game(5, 300, None));
+        stats.record(&game(7, 200, None));
+        stats.record(&game(5, 150, None));
+        assert_eq!(stats.best_score(), 7);
+        assert_eq!(stats.scores[1].speed, 300, "Older result stays above.");
+
+        for _ in 0..MAX_SCORES {
+            stats.record(&game(10, 300, None));
+        }
+        stats.record(&game(1, 300, None));
+        assert_eq!(stats.scores.len(), MAX_SCORES);
+        assert!(stats.scores.iter().all(|entry| entry.score == 10));
+    }
+
+    #[test]
+    fn test_profiles_save_and_load() {
+        let path = env::temp_dir().join(format!("snake_stats_{}.json", std::process::id()));
+        let mut book = StatsBook::default();
+        book.record("alice", &game(3, 300, Some(DeathCause::Tail)));
+        book.record("bob", &game(5, 200, Some(DeathCause::Wall)));
+        book.record("alice", &game(1, 300, Some(DeathCause::Wall)));
+        book.save_to(&path);
+
+        let book = StatsBook::load_from(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(book.profile("alice").games_played, 2);
+        assert_eq!(book.profile("bob").games_played, 1);
+        assert_eq!(book.profile("nobody"), Stats::default());
+    }
+
+    #[test]
+    fn test_draw_fits_frame() {
+        let mut stats = Stats::default();
+        for speed in (100..=700).step_by(50) {
+            stats.record(&game(7, speed, None));
+        }
+        let mut frame = new_frame();
+        StatsView {
+            profile: "somebody with a very long name",
+            stats: &stats,
+        }
+        .draw(&mut frame);
+        let screen = Screen::compose(&frame, RenderMode::Normal);
+        assert!(screen.line(1).starts_with("  Profile: somebody"));
+    }
+}
@@ -0,0 +1,43 @@
+//! Everything the game keeps between sessions lives in one directory:
+//! `$XDG_CONFIG_HOME/snake`, `~/.config/snake` or `%APPDATA%\snake`.
+//! Files are plain JSON, so they are easy to look at or fix by hand.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "snake";
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join(APP_DIR));
+    }
+    if let Some(dir) = env::var_os("APPDATA").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join(APP_DIR));
+    }
+    env::var_os("HOME")
+        .filter(|d| !d.is_empty())
+        .map(|home| PathBuf::from(home).join(".config").join(APP_DIR))
+}
+
+/// Path of a file inside config directory.
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
+
+pub fn load_json<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> io::Result<T> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save_json<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> io::Result<()> {
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, content)
+}
@@ -1,10 +1,12 @@
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+
 ///I use simple timer concept where we have took instant time
 ///each cycle and send delta of time that pass to each Timer
 ///object that saves it. And when next it current saved time goes more
 ///then max/set time for timer - it is marked as finished.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Timer {
     total: Duration,
     max: Duration,
@@ -27,13 +29,70 @@ impl Timer {
         };
     }
 
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.max.saturating_sub(self.total)
+    }
+
     pub fn finished(&self) -> bool {
         self.finished
     }
 
+    /// Starts the next period. Time over `max` is kept, so a timer
+    /// ticked in uneven steps still finishes once every `max` on average.
     pub fn reset(&mut self) {
-        self.total = Duration::new(0, 0);
-        self.finished = false;
+        self.total = self.total.saturating_sub(self.max);
+        self.finished = self.total >= self.max;
+    }
+}
+
+/// Most time caught up in one `advance`. The game never waits longer than
+/// this, after a longer stall, like a suspended terminal, the rest is
+/// dropped instead of racing to catch up.
+const MAX_BEHIND: Duration = Duration::from_secs(1);
+
+/// Cuts real time into steps of the same length. Time left over from a
+/// frame is carried to the next one, so the game runs at the same pace
+/// however often it wakes up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FixedStep {
+    step: Duration,
+    accumulator: Duration,
+}
+
+impl FixedStep {
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Adds the time passed and returns how many steps are due.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator = (self.accumulator + elapsed).min(MAX_BEHIND);
+        let steps = (self.accumulator.as_nanos() / self.step.as_nanos()) as u32;
+        self.accumulator -= self.step * steps;
+        steps
+    }
+
+    /// Time until the next step is due.
+    pub fn until_next(&self) -> Duration {
+        self.step.saturating_sub(self.accumulator)
+    }
+
+    /// Real time until the step which takes the game `ahead` of where it
+    /// is now, the next step at least.
+    pub fn until(&self, ahead: Duration) -> Duration {
+        let steps = ahead.as_nanos().div_ceil(self.step.as_nanos()).max(1) as u32;
+        self.until_next() + self.step * (steps - 1)
     }
 }
 
@@ -41,7 +100,7 @@ impl Timer {
 mod test {
     use std::time::Duration;
 
-    use crate::timer::Timer;
+    use crate::timer::{FixedStep, Timer};
 
     #[test]
     fn test_timer() {
@@ -54,4 +113,42 @@ mod test {
         timer.reset();
         assert!(!timer.finished());
     }
+
+    #[test]
+    fn test_reset_keeps_overflow() {
+        let mut timer = Timer::new(Duration::from_millis(100));
+        timer.tick(Duration::from_millis(130));
+        timer.reset();
+        assert!(!timer.finished());
+        assert_eq!(timer.remaining(), Duration::from_millis(70));
+        timer.tick(Duration::from_millis(180));
+        timer.reset();
+        assert!(timer.finished(), "Two periods passed at once.");
+        timer.reset();
+        assert_eq!(timer.remaining(), Duration::from_millis(90));
+    }
+
+    #[test]
+    fn test_fixed_step() {
+        let mut clock = FixedStep::new(Duration::from_millis(10));
+        assert_eq!(clock.advance(Duration::from_millis(4)), 0);
+        assert_eq!(clock.until_next(), Duration::from_millis(6));
+        assert_eq!(clock.advance(Duration::from_millis(27)), 3);
+        assert_eq!(clock.until_next(), Duration::from_millis(9));
+        assert_eq!(clock.until(Duration::ZERO), Duration::from_millis(9));
+        assert_eq!(
+            clock.until(Duration::from_millis(30)),
+            Duration::from_millis(29)
+        );
+        assert_eq!(
+            clock.until(Duration::from_millis(31)),
+            Duration::from_millis(39)
+        );
+        assert_eq!(
+            clock.advance(Duration::from_secs(10)),
+            100,
+            "Catch up is capped."
+        );
+        assert_eq!(clock.until_next(), Duration::from_millis(10));
+    }
 }
@@ -1,20 +1,38 @@
-use std::time::Instant;
+use std::time::Duration;
 
-use crate::frame::Drawable;
+use serde::{Deserialize, Serialize};
 
+use crate::events::{GameEvent, Listener};
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct TopBar {
     score: usize,
-    instant: Instant,
+    #[serde(default)]
+    apples: usize,
+    elapsed: Duration,
+}
+
+impl Default for TopBar {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TopBar {
     pub fn new() -> Self {
         Self {
             score: 0,
-            instant: Instant::now(),
+            apples: 0,
+            elapsed: Duration::ZERO,
         }
     }
 
+    /// Game time is counted from frame deltas, so it stops while the
+    /// game is not running and continues from saved value.
+    pub fn update(&mut self, delta: Duration) {
+        self.elapsed += delta;
+    }
+
     pub fn scores(&mut self) {
         self.score += 1;
     }
@@ -22,17 +40,23 @@ impl TopBar {
     pub fn get_scores(&self) -> usize {
         self.score
     }
+    pub fn get_apples(&self) -> usize {
+        self.apples
+    }
     pub fn get_time(&self) -> usize {
-        self.instant.elapsed().as_secs() as usize
+        self.elapsed.as_secs() as usize
+    }
+    /// Game time until the shown time changes.
+    pub fn time_to_next_second(&self) -> Duration {
+        Duration::from_secs(1) - Duration::from_nanos(self.elapsed.subsec_nanos() as u64)
     }
 }
 
-impl Drawable for TopBar {
-    fn draw(&self, frame: &mut crate::frame::Frame) {
-        let time = self.instant.elapsed().as_secs();
-        let bar = format!(" Score: {:04} Time: {:05}", self.score, time);
-        for (i, s) in bar.chars().enumerate() {
-            frame[i][0] = s;
+impl Listener for TopBar {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::AteApple { .. } = event {
+            self.scores();
+            self.apples += 1;
         }
     }
 }
@@ -0,0 +1,404 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    ai,
+    battlesnake::Battlesnake,
+    bot::{Bot, Controller},
+    env::{EnvConfig, Environment},
+};
+
+/// Games longer than this are stopped, some players circle forever.
+pub const MAX_STEPS: usize = 5000;
+const START_ELO: f64 = 1500.0;
+const ELO_K: f64 = 32.0;
+
+/// Player from the command line: name of a built-in AI, url of
+/// a Battlesnake server or a command of a bot program.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Player {
+    Builtin(String),
+    Battlesnake(String),
+    Process(String),
+}
+
+impl Player {
+    pub fn parse(spec: &str) -> Self {
+        if ai::BUILTIN.contains(&spec) {
+            Player::Builtin(spec.to_string())
+        } else if spec.starts_with("http://") || spec.starts_with("https://") {
+            Player::Battlesnake(spec.to_string())
+        } else {
+            Player::Process(spec.to_string())
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Player::Builtin(name) | Player::Battlesnake(name) | Player::Process(name) => name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    /// Everyone plays everyone.
+    RoundRobin,
+    /// Players with close points meet each round, nobody meets twice while
+    /// it can be helped.
+    Swiss { rounds: usize },
+}
+
+/// Snake has the board to itself, so in a match both players play the same
+/// games, one seed per game, and the higher score wins a game. Equal
+/// scores are a draw.
+pub struct Tournament {
+    pub players: Vec<Player>,
+    pub format: Format,
+    /// Games in every match.
+    pub games: usize,
+    /// Game `n` of every match uses `seed + n`.
+    pub seed: u64,
+    pub threads: usize,
+    pub game: EnvConfig,
+    pub bot_timeout: Duration,
+    pub battlesnake_timeout: Duration,
+}
+
+/// Both players of a match in every game.
+type Games = Vec<(Outcome, Outcome)>;
+
+/// One player in one game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Outcome {
+    score: usize,
+    length: usize,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Standing {
+    pub player: String,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Win is a point, draw is a half, bye in Swiss counts as winning all
+    /// games of the match.
+    pub points: f64,
+    pub win_rate: f64,
+    pub average_length: f64,
+    pub average_score: f64,
+    pub elo: f64,
+}
+
+impl Standing {
+    fn new(player: &Player) -> Self {
+        Self {
+            player: player.name().to_string(),
+            games: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            points: 0.0,
+            win_rate: 0.0,
+            average_length: 0.0,
+            average_score: 0.0,
+            elo: START_ELO,
+        }
+    }
+
+    /// Game against a player with `elo`, `result` is 1 for win, 0.5 for
+    /// draw and 0 for loss. Returns change of the rating.
+    fn add(&mut self, outcome: Outcome, result: f64, elo: f64) -> f64 {
+        let games = self.games as f64;
+        self.average_length = (self.average_length * games + outcome.length as f64) / (games + 1.0);
+        self.average_score = (self.average_score * games + outcome.score as f64) / (games + 1.0);
+        self.games += 1;
+        match result {
+            r if r > 0.5 => self.wins += 1,
+            r if r < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+        self.points += result;
+        self.win_rate = self.wins as f64 / self.games as f64;
+        let expected = 1.0 / (1.0 + 10f64.powf((elo - self.elo) / 400.0));
+        ELO_K * (result - expected)
+    }
+}
+
+impl Tournament {
+    /// Plays all matches and returns standings, best first.
+    pub fn run(&self) -> io::Result<Vec<Standing>> {
+        let mut standings: Vec<Standing> = self.players.iter().map(Standing::new).collect();
+        let mut met = BTreeSet::new();
+        let rounds = match self.format {
+            Format::RoundRobin => 1,
+            Format::Swiss { rounds } => rounds,
+        };
+        for _ in 0..rounds {
+            let pairs = match self.format {
+                Format::RoundRobin => (0..self.players.len())
+                    .flat_map(|a| (a + 1..self.players.len()).map(move |b| (a, b)))
+                    .collect(),
+                Format::Swiss { .. } => {
+                    let (pairs, bye) = swiss_pairs(&standings, &met);
+                    if let Some(bye) = bye {
+                        standings[bye].points += self.games as f64;
+                    }
+                    pairs
+                }
+            };
+            met.extend(pairs.iter().copied());
+            let results = self.play(&pairs)?;
+            // Ratings change in the order of games, not of threads
+            for ((a, b), game) in results {
+                for (first, second) in game {
+                    let result = match first.score.cmp(&second.score) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Less => 0.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                    };
+                    let (elo_a, elo_b) = (standings[a].elo, standings[b].elo);
+                    let change_a = standings[a].add(first, result, elo_b);
+                    let change_b = standings[b].add(second, 1.0 - result, elo_a);
+                    standings[a].elo += change_a;
+                    standings[b].elo += change_b;
+                }
+            }
+        }
+        standings.sort_by(|a, b| b.points.total_cmp(&a.points).then(b.elo.total_cmp(&a.elo)));
+        Ok(standings)
+    }
+
+    /// Every game of every pair on the threads, results in order of pairs.
+    fn play(&self, pairs: &[(usize, usize)]) -> io::Result<Vec<((usize, usize), Games)>> {
+        let jobs: Vec<(usize, usize)> = (0..pairs.len())
+            .flat_map(|pair| (0..self.games).map(move |game| (pair, game)))
+            .collect();
+        let next = AtomicUsize::new(0);
+        let done = Mutex::new(vec![None; jobs.len()]);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| -> io::Result<()> {
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(&(pair, game)) = jobs.get(index) else {
+                                return Ok(());
+                            };
+                            let (a, b) = pairs[pair];
+                            let seed = self.seed.wrapping_add(game as u64);
+                            let outcome = (
+                                self.play_game(&self.players[a], seed)?,
+                                self.play_game(&self.players[b], seed)?,
+                            );
+                            done.lock().expect("Worker panicked.")[index] = Some(outcome);
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("Worker panicked."))
+        })?;
+        let done = done.into_inner().expect("Worker panicked.");
+        Ok(pairs
+            .iter()
+            .enumerate()
+            .map(|(pair, players)| {
+                let games = done[pair * self.games..(pair + 1) * self.games]
+                    .iter()
+                    .map(|outcome| outcome.expect("Every game is played."))
+                    .collect();
+                (*players, games)
+            })
+            .collect())
+    }
+
+    fn controller(&self, player: &Player, seed: u64) -> io::Result<Box<dyn Controller>> {
+        Ok(match player {
+            Player::Builtin(name) => ai::builtin(name, seed).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no built-in AI '{}'", name),
+                )
+            })?,
+            Player::Battlesnake(url) => Box::new(Battlesnake::new(url, self.battlesnake_timeout)?),
+            Player::Process(command) => {
+                Box::new(Bot::spawn(command, self.bot_timeout).map_err(|e| {
+                    io::Error::new(e.kind(), format!("can't start bot '{}': {}", command, e))
+                })?)
+            }
+        })
+    }
+
+    fn play_game(&self, player: &Player, seed: u64) -> io::Result<Outcome> {
+        let mut controller = self.controller(player, seed)?;
+        let mut env = Environment::new(EnvConfig {
+            max_steps: Some(MAX_STEPS),
+            ..self.game.clone()
+        });
+        env.reset(seed);
+        while !env.is_done() {
+            let direction = controller
+                .ask(&env.state())
+                .unwrap_or(env.snake().direction());
+            env.step(direction);
+        }
+        controller.finish(&env.state());
+        Ok(Outcome {
+            score: env.topbar().get_scores(),
+            length: env.snake().length(),
+        })
+    }
+}
+
+/// Pairs for the next Swiss round: from the top of the table everyone
+/// gets the next player they haven't met yet, or just the next one.
+/// With odd number of players the last one left gets a bye.
+fn swiss_pairs(
+    standings: &[Standing],
+    met: &BTreeSet<(usize, usize)>,
+) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut order: Vec<usize> = (0..standings.len()).collect();
+    order.sort_by(|&a, &b| {
+        standings[b]
+            .points
+            .total_cmp(&standings[a].points)
+            .then(standings[b].elo.total_cmp(&standings[a].elo))
+            .then(a.cmp(&b))
+    });
+    let mut pairs = Vec::new();
+    while order.len() > 1 {
+        let a = order.remove(0);
+        let new = order
+            .iter()
+            .position(|&b| !met.contains(&(a.min(b), a.max(b))))
+            .unwrap_or(0);
+        let b = order.remove(new);
+        pairs.push((a.min(b), a.max(b)));
+    }
+    (pairs, order.pop())
+}
+
+/// Standings as CSV with a header line.
+pub fn to_csv(standings: &[Standing]) -> String {
+    let mut csv = String::from(
+        "rank,player,games,wins,draws,losses,points,win_rate,average_length,average_score,elo\n",
+    );
+    for (rank, s) in standings.iter().enumerate() {
+        // Commands can have commas and quotes
+        let player = format!("\"{}\"", s.player.replace('"', "\"\""));
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{:.3},{:.2},{:.2},{:.0}\n",
+            rank + 1,
+            player,
+            s.games,
+            s.wins,
+            s.draws,
+            s.losses,
+            s.points,
+            s.win_rate,
+            s.average_length,
+            s.average_score,
+            s.elo
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::Level, Size};
+
+    fn tournament(players: &[&str], format: Format) -> Tournament {
+        Tournament {
+            players: players.iter().map(|spec| Player::parse(spec)).collect(),
+            format,
+            games: 3,
+            seed: 10,
+            threads: 2,
+            // Small board keeps games short
+            game: EnvConfig {
+                level: Level::empty(Size::new(12, 12)),
+                ..Default::default()
+            },
+            bot_timeout: Duration::from_millis(50),
+            battlesnake_timeout: Duration::from_millis(500),
+        }
+    }
+
+    #[test]
+    fn test_player() {
+        assert_eq!(
+            Player::parse("greedy"),
+            Player::Builtin(String::from("greedy"))
+        );
+        assert_eq!(
+            Player::parse("http://localhost:8000"),
+            Player::Battlesnake(String::from("http://localhost:8000"))
+        );
+        assert_eq!(
+            Player::parse("https://snake.example.com/api"),
+            Player::Battlesnake(String::from("https://snake.example.com/api"))
+        );
+        assert_eq!(
+            Player::parse("python3 bot.py"),
+            Player::Process(String::from("python3 bot.py"))
+        );
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let standings = tournament(&["random", "greedy"], Format::RoundRobin)
+            .run()
+            .unwrap();
+        assert_eq!(standings.len(), 2);
+        assert_eq!(standings[0].player, "greedy");
+        assert_eq!(standings[0].games, 3);
+        assert_eq!(
+            standings[0].wins + standings[1].wins + standings[0].draws,
+            3
+        );
+        assert!(standings[0].elo > standings[1].elo);
+        assert!(standings[0].average_length > 4.0);
+        let csv = to_csv(&standings);
+        assert!(csv.starts_with("rank,player,"));
+        assert!(csv.lines().nth(1).unwrap().starts_with("1,\"greedy\",3,"));
+    }
+
+    #[test]
+    fn test_same_seed_same_standings() {
+        let players = ["random", "greedy", "random"];
+        let run = || tournament(&players, Format::RoundRobin).run().unwrap();
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_swiss_pairs() {
+        let players: Vec<Player> = ["a", "b", "c"].map(Player::parse).to_vec();
+        let mut standings: Vec<Standing> = players.iter().map(Standing::new).collect();
+        standings[2].points = 2.0;
+        let (pairs, bye) = swiss_pairs(&standings, &BTreeSet::new());
+        assert_eq!((pairs, bye), (vec![(0, 2)], Some(1)));
+        let (pairs, bye) = swiss_pairs(&standings, &BTreeSet::from([(0, 2)]));
+        assert_eq!((pairs, bye), (vec![(1, 2)], Some(0)));
+
+        let standings = tournament(&["greedy", "random", "random"], Format::Swiss { rounds: 2 })
+            .run()
+            .unwrap();
+        let games: usize = standings.iter().map(|s| s.games).sum();
+        assert_eq!(games, 2 * 2 * 3, "One match a round, both sides count.");
+    }
+}
@@ -0,0 +1,149 @@
+//! Golden tests for drawing. Each drawable is rendered into memory and
+//! compared with the text in `tests/snapshots`. When drawing is changed on
+//! purpose, run tests with `UPDATE_SNAPSHOTS=1` and check the new files.
+use std::{env, fs, path::PathBuf};
+
+use snake::{
+    apple::AppleDispencer,
+    events::{EventQueue, GameEvent},
+    frame::{new_frame, Drawable},
+    hud::{Hud, HudWidget},
+    menu::Menu,
+    render::{render, MemoryBackend},
+    screen::{RenderMode, Screen},
+    settings::Settings,
+    snake::{Direction, Snake},
+    topbar::TopBar,
+    Point,
+};
+
+fn assert_snapshot(name: &str, drawable: &dyn Drawable) {
+    assert_snapshot_in(name, &[drawable], RenderMode::Normal);
+}
+
+fn assert_snapshot_in(name: &str, drawables: &[&dyn Drawable], mode: RenderMode) {
+    let mut frame = new_frame();
+    for drawable in drawables {
+        drawable.draw(&mut frame);
+    }
+    let screen = Screen::layout(&frame, mode, Screen::fit_size(&frame, mode));
+    let mut backend = MemoryBackend::new(screen.cols(), screen.rows());
+    render(&mut backend, &screen, &screen, true).unwrap();
+    let text = backend.text() + "\n";
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.txt", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &text).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Can't read snapshot {}: {}", path.display(), e));
+    assert_eq!(text, expected, "Drawing differs from {}", path.display());
+}
+
+#[test]
+fn test_menu() {
+    let mut menu = Menu::new(300, 3, Settings::default());
+    menu.set_music_tracks(vec![String::from("game"), String::from("menu")], "game");
+    menu.get_game_results(12, 40);
+    menu.next_option();
+    assert_snapshot("menu", &menu);
+}
+
+#[test]
+fn test_menu_sound_submenu() {
+    let mut menu = Menu::new(300, 3, Settings::default());
+    menu.set_music_tracks(vec![String::from("game"), String::from("menu")], "game");
+    // "New game", "Refresh rate", "Apples" and then "Sound"
+    for _ in 0..3 {
+        menu.next_option();
+    }
+    assert_eq!(menu.enter(), None);
+    menu.toggle_mute();
+    assert_snapshot("menu_sound", &menu);
+}
+
+#[test]
+fn test_hud() {
+    let mut topbar = TopBar::new();
+    topbar.update(std::time::Duration::from_secs(75));
+    for _ in 0..7 {
+        topbar.scores();
+    }
+    let hud = Hud {
+        widgets: &HudWidget::ALL,
+        topbar: &topbar,
+        snake: &Snake::new(300),
+        apples: &apples(),
+        high_score: 12,
+    };
+    assert_snapshot("hud", &hud);
+}
+
+#[test]
+fn test_snake() {
+    let mut snake = Snake::new(100);
+    let mut events = EventQueue::new();
+    snake.queue_turn(Direction::Up);
+    snake.update(std::time::Duration::from_millis(100), &mut events);
+    snake.update(std::time::Duration::from_millis(100), &mut events);
+    assert_eq!(
+        events.events().last(),
+        Some(&GameEvent::Moved {
+            head: Point::new(20, 18)
+        })
+    );
+    assert_snapshot("snake", &snake);
+}
+
+// Apples are placed randomly, so the dispencer is loaded as saved game is.
+fn apples() -> AppleDispencer {
+    serde_json::from_str(
+        r#"{
+            "deployed": [
+                { "place": { "x": 3, "y": 4 }, "kind": "Regular",
+                  "timer": { "total": { "secs": 0, "nanos": 0 },
+                             "max": { "secs": 10, "nanos": 0 }, "finished": false } },
+                { "place": { "x": 25, "y": 12 }, "kind": "Regular",
+                  "timer": { "total": { "secs": 0, "nanos": 0 },
+                             "max": { "secs": 10, "nanos": 0 }, "finished": false } }
+            ],
+            "max_count": 2,
+            "seed": 42
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_apples() {
+    assert_snapshot("apples", &apples());
+}
+
+#[test]
+fn test_double_width() {
+    let mut topbar = TopBar::new();
+    for _ in 0..3 {
+        topbar.scores();
+    }
+    let (snake, apples) = (Snake::new(100), apples());
+    let hud = Hud {
+        widgets: &HudWidget::DEFAULT,
+        topbar: &topbar,
+        snake: &snake,
+        apples: &apples,
+        high_score: 0,
+    };
+    let drawables: [&dyn Drawable; 3] = [&snake, &apples, &hud];
+    assert_snapshot_in("double_width", &drawables, RenderMode::DoubleWidth);
+}
+
+#[test]
+fn test_half_block() {
+    let drawables: [&dyn Drawable; 2] = [&Snake::new(100), &apples()];
+    assert_snapshot_in("half_block", &drawables, RenderMode::HalfBlock);
+}